@@ -0,0 +1,48 @@
+use std::ffi::OsString;
+use std::path::Path;
+use filenewer::{check_dir_exists, evaluate_path_vars, normalize_path, parse_ls_args, LS_USAGE};
+
+// runs `FileNewer ls ...` without ever opening a window, returns the process exit code
+pub(crate) fn run_ls(args: &[OsString]) -> i32 {
+    let opts = match parse_ls_args(args) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{}", LS_USAGE);
+            return 0;
+        }
+        Err(e) => {
+            eprintln!("FileNewer ls: {}\n\n{}", e, LS_USAGE);
+            return 2;
+        }
+    };
+
+    // only a path that is text can have variables to expand
+    let evaluated = match opts.path.to_str() {
+        Some(path) => evaluate_path_vars(path),
+        None => Ok(normalize_path(Path::new(&opts.path))),
+    };
+    let path = match evaluated {
+        Ok(path) => path,
+        Err(e) => {
+            eprintln!("FileNewer ls: {}", e);
+            return 1;
+        }
+    };
+    if !check_dir_exists(&path) {
+        eprintln!("FileNewer ls: cannot find folder {}", path.display());
+        return 1;
+    }
+    match opts.display_options.list(&path) {
+        Ok(listing) => {
+            print!("{}", opts.render(&listing.files));
+            for e in &listing.errors {
+                eprintln!("FileNewer ls: {}", e);
+            }
+            if listing.errors.is_empty() { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("FileNewer ls: cannot read contents of folder as {}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! The columns of the file table and how each one is shown.
+
+use std::collections::HashMap;
+use crate::file_manager::FileInfo;
+use crate::file_ordering::{DisplayOptions, SortBy};
+
+/// A column of the file table, shared by the explorer and the `ls` command
+#[derive(PartialEq, Eq, Hash)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum Column {
+    /// The one letter kind of entry, `d` for a folder and so on
+    Type,
+    /// The file name, the one column that is always shown
+    Name,
+    /// The extension, without the dot
+    Ext,
+    /// When the entry was created, where the file system records it
+    Created,
+    /// When the contents were last changed
+    Modified,
+    /// When the entry was last read
+    Accessed,
+    /// The size of a file, or of everything in a folder once it has been worked out
+    Size,
+    /// The `rwx` permission string
+    Permissions,
+    /// The user owning the entry
+    Owner,
+    /// The group owning the entry
+    Group,
+}
+
+impl Column {
+    /// Every column, in the order the explorer lists them
+    pub const ALL: [Column; 10] = [Column::Type, Column::Name, Column::Ext, Column::Created,
+        Column::Modified, Column::Accessed, Column::Size, Column::Permissions, Column::Owner, Column::Group];
+
+    /// The title shown at the top of the column
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::Type => { "Type" }
+            Column::Name => { "File Name" }
+            Column::Ext => { "Extension" }
+            Column::Created => { "Creation Date" }
+            Column::Modified => { "Modified Date" }
+            Column::Accessed => { "Access Date" }
+            Column::Size => { "File Size" }
+            Column::Permissions => { "Permissions" }
+            Column::Owner => { "Owner" }
+            Column::Group => { "Group" }
+        }
+    }
+
+    /// Short machine friendly name, used on the command line and as JSON / CSV keys
+    pub fn key(&self) -> &'static str {
+        match self {
+            Column::Type => { "type" }
+            Column::Name => { "name" }
+            Column::Ext => { "ext" }
+            Column::Created => { "ctime" }
+            Column::Modified => { "mtime" }
+            Column::Accessed => { "atime" }
+            Column::Size => { "size" }
+            Column::Permissions => { "mode" }
+            Column::Owner => { "owner" }
+            Column::Group => { "group" }
+        }
+    }
+
+    /// The column with this [`key`](Column::key), `None` for anything else
+    pub fn from_key(key: &str) -> Option<Column> {
+        Column::ALL.into_iter().find(|col| col.key() == key)
+    }
+
+    /// What the listing is ordered by when this column's header is clicked
+    pub fn sort_by(&self) -> SortBy {
+        match self {
+            Column::Type => { SortBy::Type }
+            Column::Name => { SortBy::Name }
+            Column::Ext => { SortBy::Ext }
+            Column::Created => { SortBy::CreateDate }
+            Column::Modified => { SortBy::ModDate }
+            Column::Accessed => { SortBy::ViewDate }
+            Column::Size => { SortBy::Size }
+            Column::Permissions => { SortBy::Permissions }
+            Column::Owner => { SortBy::Owner }
+            Column::Group => { SortBy::Group }
+        }
+    }
+}
+
+/// Column widths as comma separated `key=width` pairs, read back with [`parse_widths`]
+pub fn format_widths(widths: &HashMap<Column, f32>) -> String {
+    let pairs: Vec<String> = Column::ALL.iter()
+        .filter_map(|col| widths.get(col).map(|width| format!("{}={}", col.key(), width)))
+        .collect();
+    pairs.join(",")
+}
+
+/// The widths [`format_widths`] wrote, anything that is not a known column and a width is skipped
+pub fn parse_widths(text: &str) -> HashMap<Column, f32> {
+    text.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .filter_map(|(key, width)| Some((Column::from_key(key)?, width.parse().ok()?)))
+        .collect()
+}
+
+impl FileInfo {
+    /// The text shown for this entry in `column`
+    pub fn column_text(&self, column: Column, options: &DisplayOptions) -> String {
+        match column {
+            Column::Type => { self.single_char_desc().to_owned() }
+            Column::Name => { self.file_name.to_string_lossy().into_owned() }
+            Column::Ext => { self.file_ext.clone().unwrap_or_default() }
+            Column::Created => { self.creation_time_formated(&options.date_format) }
+            Column::Modified => { self.last_mod_formated(&options.date_format) }
+            Column::Accessed => { self.last_access_formated(&options.date_format) }
+            Column::Size => {
+                self.size_formated(&options.size_format)
+            }
+            Column::Permissions => { self.permissions_formated() }
+            Column::Owner => { self.owner.clone().unwrap_or_else(|| "-".to_string()) }
+            Column::Group => { self.group.clone().unwrap_or_else(|| "-".to_string()) }
+        }
+    }
+}
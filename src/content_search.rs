@@ -0,0 +1,215 @@
+//! Searching inside files for text or a regular expression.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use regex::{Regex, RegexBuilder};
+use crate::error::FileNewerError;
+use crate::job::Job;
+use crate::walk::Walk;
+
+// how much of a file is checked for NUL bytes before deciding it is binary, the same as git
+const BINARY_CHECK_LEN: usize = 8000;
+// stops a huge log full of matches from swamping the results
+const MAX_MATCHES_PER_FILE: usize = 1000;
+
+/// What to look for inside files
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct ContentQuery {
+    /// The text, or regular expression, to find
+    pub pattern: String,
+    /// Read `pattern` as a regular expression rather than literal text
+    pub regex: bool,
+    /// Tell upper and lower case apart, off by default
+    pub case_sensitive: bool,
+    /// Lines shown either side of each match
+    pub context: usize,
+}
+
+impl Default for ContentQuery {
+    fn default() -> Self {
+        Self { pattern: String::new(), regex: false, case_sensitive: false, context: 2 }
+    }
+}
+
+impl ContentQuery {
+    /// The regular expression to search with, failing when `pattern` is not a valid one
+    pub fn compile(&self) -> Result<Regex, FileNewerError> {
+        let source = if self.regex { self.pattern.clone() } else { regex::escape(&self.pattern) };
+        RegexBuilder::new(&source)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|source| FileNewerError::InvalidPattern { pattern: self.pattern.clone(), source })
+    }
+}
+
+/// One matching line along with the lines around it
+#[derive(Debug)]
+pub struct LineMatch {
+    /// Counted from 1, like editors do
+    pub line_number: usize,
+    /// The matching line, without its line ending
+    pub line: String,
+    /// The lines leading up to it, nearest last
+    pub before: Vec<String>,
+    /// The lines following it, nearest first
+    pub after: Vec<String>,
+}
+
+/// Every match in one file
+#[derive(Debug)]
+pub struct ContentHit {
+    /// Where the file is, relative to the folder the search started from
+    pub relative_path: PathBuf,
+    /// The matches in the order they appear
+    pub matches: Vec<LineMatch>,
+}
+
+/// What a [`ContentSearch`] has to report since it was last polled
+#[derive(Debug)]
+pub enum ContentEvent {
+    /// A file with at least one match
+    Hit(ContentHit),
+    /// A folder or file that could not be read, the search carries on without it
+    Unreadable(FileNewerError),
+    /// Every file has been searched
+    Done,
+    /// The folder the search started from could not be opened
+    Failed(FileNewerError),
+}
+
+/// Searches the contents of every file below a folder, several files at a time.
+///
+/// Binary files are skipped, as are symlinks. Hidden entries, and everything inside them, are
+/// skipped unless `inc_hidden` is set. Dropping the search cancels it.
+pub struct ContentSearch {
+    root: PathBuf,
+    job: Job<ContentEvent>,
+}
+
+impl ContentSearch {
+    /// Starts searching every file below `root` for `regex`, with `context` lines either side of each match
+    pub fn spawn(root: PathBuf, regex: Regex, context: usize, inc_hidden: bool) -> Self {
+        let worker_root = root.clone();
+        let is_last = |event: &ContentEvent| matches!(event, ContentEvent::Done | ContentEvent::Failed(_));
+        let job = Job::spawn(is_last, move |sender, worker_cancelled| {
+            let (queue_sender, queue) = channel::<PathBuf>();
+            // workers take turns pulling the next file off the one queue
+            let queue = Arc::new(Mutex::new(queue));
+            let worker_count = thread::available_parallelism().map_or(4, |n| n.get());
+            let workers: Vec<_> = (0..worker_count).map(|_| {
+                let queue = queue.clone();
+                let sender = sender.clone();
+                let regex = regex.clone();
+                let root = worker_root.clone();
+                let cancelled = worker_cancelled.clone();
+                thread::spawn(move || loop {
+                    let next = queue.lock().map(|queue| queue.recv());
+                    let Ok(Ok(relative_path)) = next else { return };
+                    if cancelled.load(Ordering::Relaxed) { return; }
+                    let event = match search_file(&root.join(&relative_path), &regex, context) {
+                        Ok(matches) if matches.is_empty() => { continue }
+                        Ok(matches) => { ContentEvent::Hit(ContentHit { relative_path, matches }) }
+                        Err(e) => { ContentEvent::Unreadable(e) }
+                    };
+                    if sender.send(event).is_err() { return; }
+                })
+            }).collect();
+
+            if let Err(e) = queue_files(&worker_root, inc_hidden, &queue_sender, &sender, &worker_cancelled) {
+                let _ = sender.send(ContentEvent::Failed(e));
+                return;
+            }
+            // workers stop once the queue is empty and closed
+            drop(queue_sender);
+            for worker in workers { let _ = worker.join(); }
+            if !worker_cancelled.load(Ordering::Relaxed) { let _ = sender.send(ContentEvent::Done); }
+        });
+        Self { root, job }
+    }
+
+    /// The folder the search started from
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Everything the workers have sent since the last poll, never blocks
+    pub fn poll(&mut self) -> Vec<ContentEvent> {
+        self.job.poll()
+    }
+
+    /// `true` once `Done` or `Failed` has been polled, or the search was cancelled
+    pub fn is_finished(&self) -> bool {
+        self.job.is_finished()
+    }
+
+    /// Stops every worker at the next file, hits already polled are kept by the caller
+    pub fn cancel(&mut self) {
+        self.job.cancel();
+    }
+}
+
+// walks the tree handing every regular file to the workers, only failing if root can not be read
+fn queue_files(root: &Path, inc_hidden: bool, queue: &Sender<PathBuf>, events: &Sender<ContentEvent>,
+               cancelled: &AtomicBool) -> Result<(), FileNewerError> {
+    for entry in Walk::new(root, inc_hidden)? {
+        if cancelled.load(Ordering::Relaxed) { return Ok(()); }
+        match entry {
+            Ok(entry) if entry.meta.is_file() => { let _ = queue.send(entry.relative_path); }
+            Ok(_) => {}
+            Err(e) => { let _ = events.send(ContentEvent::Unreadable(e)); }
+        }
+    }
+    Ok(())
+}
+
+/// Every line of the file at `path` that `regex` matches, with `context` lines either side.
+///
+/// Files with a NUL byte near the start are taken to be binary and have no matches.
+pub fn search_file(path: &Path, regex: &Regex, context: usize) -> Result<Vec<LineMatch>, FileNewerError> {
+    let io_error = |e| FileNewerError::from_entry_io(path, e);
+    let mut file = File::open(path).map_err(io_error)?;
+    let mut head = Vec::with_capacity(BINARY_CHECK_LEN);
+    (&mut file).take(BINARY_CHECK_LEN as u64).read_to_end(&mut head).map_err(io_error)?;
+    if head.contains(&0) { return Ok(Vec::new()); }
+    file.rewind().map_err(io_error)?;
+
+    let mut matches: Vec<LineMatch> = Vec::new();
+    let mut before = VecDeque::with_capacity(context + 1);
+    // matches still waiting on lines after them, with how many more they need
+    let mut waiting: Vec<(usize, usize)> = Vec::new();
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    let mut line_number = 0;
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf).map_err(io_error)? == 0 { break; }
+        line_number += 1;
+        let line = String::from_utf8_lossy(&buf).trim_end_matches(['\n', '\r']).to_string();
+        for (idx, wanted) in waiting.iter_mut() {
+            matches[*idx].after.push(line.clone());
+            *wanted -= 1;
+        }
+        waiting.retain(|(_, wanted)| *wanted > 0);
+        if matches.len() < MAX_MATCHES_PER_FILE && regex.is_match(&line) {
+            matches.push(LineMatch {
+                line_number,
+                line: line.clone(),
+                before: before.iter().cloned().collect(),
+                after: Vec::new(),
+            });
+            if context > 0 { waiting.push((matches.len() - 1, context)); }
+        }
+        if matches.len() >= MAX_MATCHES_PER_FILE && waiting.is_empty() { break; }
+        before.push_back(line);
+        if before.len() > context { before.pop_front(); }
+    }
+    Ok(matches)
+}
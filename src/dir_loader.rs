@@ -0,0 +1,87 @@
+//! Listing a directory in the background.
+
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use crate::error::FileNewerError;
+use crate::file_manager::{read_dir_entries, DirListing};
+use crate::job::Job;
+
+// a batch is sent once it is this big or this old, whichever comes first
+pub(crate) const BATCH_SIZE: usize = 512;
+pub(crate) const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What a [`DirLoader`] has to report since it was last polled
+#[derive(Debug)]
+pub enum LoadEvent {
+    /// More entries, unsorted and in the order the file system returned them
+    Batch(DirListing),
+    /// Every entry has been sent
+    Done,
+    /// The directory itself could not be opened, nothing more will be sent
+    Failed(FileNewerError),
+}
+
+/// Lists a directory on a worker thread, streaming the entries back in batches.
+///
+/// Dropping the loader cancels it, so starting a new listing is enough to abandon the old one.
+pub struct DirLoader {
+    path: PathBuf,
+    job: Job<LoadEvent>,
+}
+
+impl DirLoader {
+    /// Starts listing `path`, hidden entries only when `inc_hidden` is set
+    pub fn spawn(path: PathBuf, inc_hidden: bool) -> Self {
+        let worker_path = path.clone();
+        let job = Job::spawn(|event| !matches!(event, LoadEvent::Batch(_)), move |sender, cancelled| {
+            let entries = match read_dir_entries(&worker_path, inc_hidden) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    let _ = sender.send(LoadEvent::Failed(e));
+                    return;
+                }
+            };
+            let mut batch = DirListing::default();
+            let mut batch_started = Instant::now();
+            for entry in entries {
+                if cancelled.load(Ordering::Relaxed) { return; }
+                match entry {
+                    Ok(file) => batch.files.push(file),
+                    Err(e) => batch.errors.push(e),
+                }
+                if batch.files.len() + batch.errors.len() >= BATCH_SIZE
+                    || batch_started.elapsed() >= BATCH_INTERVAL {
+                    // the receiver only goes away when the loader is dropped
+                    if sender.send(LoadEvent::Batch(std::mem::take(&mut batch))).is_err() { return; }
+                    batch_started = Instant::now();
+                }
+            }
+            if !batch.files.is_empty() || !batch.errors.is_empty() {
+                let _ = sender.send(LoadEvent::Batch(batch));
+            }
+            let _ = sender.send(LoadEvent::Done);
+        });
+        Self { path, job }
+    }
+
+    /// The directory being listed
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Everything the worker has sent since the last poll, never blocks
+    pub fn poll(&mut self) -> Vec<LoadEvent> {
+        self.job.poll()
+    }
+
+    /// `true` once `Done` or `Failed` has been polled, or the loader was cancelled
+    pub fn is_finished(&self) -> bool {
+        self.job.is_finished()
+    }
+
+    /// Stops the worker at the next entry, entries already polled are kept by the caller
+    pub fn cancel(&mut self) {
+        self.job.cancel();
+    }
+}
@@ -0,0 +1,63 @@
+//! Working out how much is stored below a directory.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::job::Job;
+use crate::walk::Walk;
+
+/// The recursive apparent size of a directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct DirSize {
+    /// Sum of the lengths of everything below the directory
+    pub bytes: u64,
+    /// Number of non directory entries below the directory
+    pub files: u64,
+}
+
+/// Adds up everything below `path` without following symlinks, unreadable entries are skipped.
+///
+/// Returns `None` if `cancelled` is set part way through.
+pub fn dir_size(path: &Path, cancelled: &AtomicBool) -> Option<DirSize> {
+    let mut total = DirSize::default();
+    let Ok(walk) = Walk::new(path, true) else { return Some(total) };
+    for entry in walk {
+        if cancelled.load(Ordering::Relaxed) { return None; }
+        let Ok(entry) = entry else { continue };
+        if !entry.meta.is_dir() {
+            total.bytes += entry.meta.len();
+            total.files += 1;
+        }
+    }
+    Some(total)
+}
+
+/// Works out the size of each directory on a worker thread, one after another.
+///
+/// Results are sent back as each directory is finished, dropping the job cancels it.
+pub struct DirSizeJob {
+    job: Job<(PathBuf, DirSize)>,
+}
+
+impl DirSizeJob {
+    /// Starts adding up each of `dirs` in turn
+    pub fn spawn(dirs: Vec<PathBuf>) -> Self {
+        // no result is the last, the job is finished once the worker runs out of directories
+        let job = Job::spawn(|_| false, move |sender, cancelled| {
+            for dir in dirs {
+                let Some(size) = dir_size(&dir, &cancelled) else { return };
+                if sender.send((dir, size)).is_err() { return; }
+            }
+        });
+        Self { job }
+    }
+
+    /// Every directory finished since the last poll, never blocks
+    pub fn poll(&mut self) -> Vec<(PathBuf, DirSize)> {
+        self.job.poll()
+    }
+
+    /// `true` once every directory has been sent
+    pub fn is_finished(&self) -> bool {
+        self.job.is_finished()
+    }
+}
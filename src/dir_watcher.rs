@@ -0,0 +1,113 @@
+//! Keeping a listing up to date as its directory changes.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+use notify::{Config, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use crate::error::FileNewerError;
+use crate::file_manager::{get_file_info, FileInfo};
+
+// how often the fallback watcher rescans, when a native watch could not be set up
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// What changed in a watched directory since it was last polled
+#[derive(Debug, Default)]
+pub struct DirChanges {
+    /// Entries that were created, modified or removed, check the disk to tell which
+    pub paths: BTreeSet<PathBuf>,
+    /// Events were lost or the directory itself changed, it needs listing again from scratch
+    pub rescan: bool,
+}
+
+impl DirChanges {
+    /// `true` when nothing changed
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && !self.rescan
+    }
+}
+
+/// Watches the entries of a single directory, not its subdirectories.
+///
+/// Uses the platform's native notifications (inotify on Linux), and only polls when a native
+/// watch can not be set up at all. Network mounts usually accept a native watch but never
+/// report changes made from other machines, so those go unnoticed until the folder is reopened.
+pub struct DirWatcher {
+    path: PathBuf,
+    // only kept so the watch stays alive
+    _watcher: Box<dyn Watcher + Send>,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl DirWatcher {
+    /// Starts watching `path`, failing when neither kind of watch can be set up
+    pub fn watch(path: &Path) -> Result<Self, FileNewerError> {
+        let (sender, events) = channel();
+        let native = RecommendedWatcher::new(sender.clone(), Config::default())
+            .and_then(|mut watcher| {
+                watcher.watch(path, RecursiveMode::NonRecursive)?;
+                Ok(Box::new(watcher) as Box<dyn Watcher + Send>)
+            });
+        let watcher = match native {
+            Ok(watcher) => watcher,
+            Err(_) => {
+                let mut watcher = PollWatcher::new(sender,
+                    Config::default().with_poll_interval(POLL_INTERVAL))
+                    .map_err(|e| watch_error(path, e))?;
+                watcher.watch(path, RecursiveMode::NonRecursive).map_err(|e| watch_error(path, e))?;
+                Box::new(watcher)
+            }
+        };
+        Ok(Self { path: path.to_path_buf(), _watcher: watcher, events })
+    }
+
+    /// The directory being watched
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Everything that changed since the last poll, merged so each entry shows up once
+    pub fn poll(&self) -> DirChanges {
+        let mut changes = DirChanges::default();
+        for event in self.events.try_iter() {
+            let Ok(event) = event else { changes.rescan = true; continue };
+            changes.rescan |= event.need_rescan();
+            for path in event.paths {
+                if path.parent() == Some(self.path.as_path()) { changes.paths.insert(path); }
+                else { changes.rescan = true; }
+            }
+        }
+        changes
+    }
+}
+
+fn watch_error(path: &Path, err: notify::Error) -> FileNewerError {
+    match err.kind {
+        notify::ErrorKind::Io(e) => FileNewerError::from_io(path, e),
+        notify::ErrorKind::PathNotFound => FileNewerError::NotFound(path.to_path_buf()),
+        _ => FileNewerError::Io { path: path.to_path_buf(), source: std::io::Error::other(err) },
+    }
+}
+
+/// Re-reads each changed path and updates `files` to match, leaving every other entry alone.
+///
+/// Entries are matched by name, removed ones are dropped and new ones are appended, so the
+/// caller should sort again afterwards. Entries that could not be read are returned.
+pub fn refresh_entries(files: &mut Vec<FileInfo>, changed: &BTreeSet<PathBuf>, inc_hidden: bool)
+    -> Vec<FileNewerError> {
+    let mut errors = Vec::new();
+    for path in changed {
+        let Some(name) = path.file_name() else { continue };
+        let existing = files.iter().position(|file| file.file_name == name);
+        match (get_file_info(path), existing) {
+            (Ok(file), Some(idx)) if inc_hidden || !file.is_hidden => files[idx] = file,
+            (Ok(file), None) if inc_hidden || !file.is_hidden => files.push(file),
+            (Ok(_), Some(idx)) => { files.remove(idx); }
+            (Ok(_), None) => {}
+            (Err(FileNewerError::VanishedEntry(_)), Some(idx)) => { files.remove(idx); }
+            (Err(FileNewerError::VanishedEntry(_)), None) => {}
+            (Err(e), _) => errors.push(e),
+        }
+    }
+    errors
+}
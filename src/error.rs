@@ -0,0 +1,107 @@
+//! The one error type every fallible function in the crate returns.
+
+use std::{fmt, io};
+use std::env::VarError;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Everything that can go wrong while resolving a path or reading a directory
+#[derive(Debug)]
+pub enum FileNewerError {
+    /// A variable or home directory in a user typed path could not be expanded
+    PathExpansion {
+        /// The path as it was typed
+        path: String,
+        /// Why it could not be expanded
+        source: VarError,
+    },
+    /// The entry at the path could not be read or changed by this user
+    PermissionDenied(PathBuf),
+    /// Nothing exists at the path
+    NotFound(PathBuf),
+    /// The entry was listed but removed before its metadata could be read
+    VanishedEntry(PathBuf),
+    /// A name that had to be text but is not valid UTF-8
+    NonUtf8Name(OsString),
+    /// Something is already at the path an entry was meant to be created, renamed or moved to
+    AlreadyExists(PathBuf),
+    /// A file name that is empty, `.`, `..` or has a path separator in it
+    InvalidName(String),
+    /// A folder was to be copied or moved into itself
+    IntoItself(PathBuf),
+    /// A search or filter pattern that is not a valid regular expression
+    InvalidPattern {
+        /// The pattern as it was typed
+        pattern: String,
+        /// What the regex parser made of it
+        source: regex::Error,
+    },
+    /// There is no trash the entry could be moved to
+    NoTrash(PathBuf),
+    /// Any other io error
+    Io {
+        /// The path being worked on when it happened
+        path: PathBuf,
+        /// The error itself
+        source: io::Error,
+    },
+}
+
+impl FileNewerError {
+    /// Sorts an io error raised while working on `path` into the matching variant
+    pub fn from_io(path: &Path, err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => { FileNewerError::NotFound(path.to_path_buf()) }
+            io::ErrorKind::PermissionDenied => { FileNewerError::PermissionDenied(path.to_path_buf()) }
+            _ => { FileNewerError::Io { path: path.to_path_buf(), source: err } }
+        }
+    }
+
+    // like from_io, but a missing file means it was removed between read_dir and stat
+    pub(crate) fn from_entry_io(path: &Path, err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => { FileNewerError::VanishedEntry(path.to_path_buf()) }
+            _ => { FileNewerError::from_io(path, err) }
+        }
+    }
+}
+
+impl fmt::Display for FileNewerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileNewerError::PathExpansion { path, source } =>
+                write!(f, "cannot expand {}: {}", path, source),
+            FileNewerError::PermissionDenied(path) =>
+                write!(f, "permission denied: {}", path.display()),
+            FileNewerError::NotFound(path) =>
+                write!(f, "cannot find {}", path.display()),
+            FileNewerError::VanishedEntry(path) =>
+                write!(f, "{} was removed while being read", path.display()),
+            FileNewerError::NonUtf8Name(name) =>
+                write!(f, "{} is not valid UTF-8", name.to_string_lossy()),
+            FileNewerError::AlreadyExists(path) =>
+                write!(f, "{} already exists", path.display()),
+            FileNewerError::InvalidName(name) =>
+                write!(f, "{:?} is not a valid name", name),
+            FileNewerError::IntoItself(path) =>
+                write!(f, "cannot put {} inside itself", path.display()),
+            FileNewerError::InvalidPattern { pattern, source } =>
+                write!(f, "invalid pattern {}: {}", pattern, source),
+            FileNewerError::NoTrash(path) =>
+                write!(f, "no trash available for {}", path.display()),
+            FileNewerError::Io { path, source } =>
+                write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for FileNewerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileNewerError::PathExpansion { source, .. } => Some(source),
+            FileNewerError::InvalidPattern { source, .. } => Some(source),
+            FileNewerError::Io { source, .. } => Some(source),
+            _ => None
+        }
+    }
+}
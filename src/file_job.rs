@@ -0,0 +1,138 @@
+//! Copying, moving, trashing and deleting entries in the background.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use crate::error::FileNewerError;
+use crate::file_ops::{copy_entry, delete_entry, move_entry, ConflictPolicy};
+use crate::job::Job;
+use crate::journal::FileOp;
+use crate::trash;
+use crate::trash::trash_entry;
+
+/// What a [`FileJob`] is to do, one entry at a time
+#[derive(Debug)]
+pub enum FileTask {
+    /// Copies, or moves when `cut`, each of `sources` into `dest_dir`.
+    ///
+    /// When overwriting, what is replaced goes to the trash first where there is one, so
+    /// undoing the paste brings it back.
+    Paste {
+        /// The entries to copy or move
+        sources: Vec<PathBuf>,
+        /// The folder they go into
+        dest_dir: PathBuf,
+        /// Move rather than copy
+        cut: bool,
+        /// What to do about entries already in `dest_dir`
+        policy: ConflictPolicy,
+    },
+    /// Moves each entry to the trash
+    Trash(Vec<PathBuf>),
+    /// Deletes each entry for good
+    Delete(Vec<PathBuf>),
+}
+
+impl FileTask {
+    /// The entries the task works through, in order
+    pub fn paths(&self) -> &[PathBuf] {
+        match self {
+            FileTask::Paste { sources, .. } => { sources }
+            FileTask::Trash(paths) | FileTask::Delete(paths) => { paths }
+        }
+    }
+
+    // carries out the task for one of its entries, adding what undoes it to ops
+    fn run_one(&self, path: &Path, ops: &mut Vec<FileOp>) -> Result<(), FileNewerError> {
+        match self {
+            FileTask::Paste { dest_dir, cut, policy, .. } => {
+                if *policy == ConflictPolicy::Overwrite && trash::SUPPORTED {
+                    if let Some(replaced) = replaced_by_paste(path, dest_dir) {
+                        ops.push(FileOp::Trash(trash_entry(&replaced)?));
+                    }
+                }
+                let op = if *cut {
+                    move_entry(path, dest_dir, *policy)?.map(|to| FileOp::Move { from: path.to_path_buf(), to })
+                } else {
+                    copy_entry(path, dest_dir, *policy)?.map(|to| FileOp::copied(path.to_path_buf(), to)).transpose()?
+                };
+                ops.extend(op);
+            }
+            FileTask::Trash(_) => { ops.push(FileOp::Trash(trash_entry(path)?)) }
+            // there is nothing left to undo this with
+            FileTask::Delete(_) => { delete_entry(path)? }
+        }
+        Ok(())
+    }
+}
+
+// the entry an overwriting paste of src into dest_dir would replace, if any
+fn replaced_by_paste(src: &Path, dest_dir: &Path) -> Option<PathBuf> {
+    // pasting into its own folder makes a second copy, and pasting into itself fails, neither replaces anything
+    if src.parent() == Some(dest_dir) || dest_dir.starts_with(src) { return None; }
+    let dest = dest_dir.join(src.file_name()?);
+    (std::fs::symlink_metadata(&dest).is_ok() && !src.starts_with(&dest)).then_some(dest)
+}
+
+/// What a [`FileJob`] has to report since it was last polled
+#[derive(Debug)]
+pub enum FileJobEvent {
+    /// Starting on `current`, with `done` of `total` entries behind it
+    Progress {
+        /// Entries finished so far
+        done: usize,
+        /// Entries in the task
+        total: usize,
+        /// The entry being worked on
+        current: PathBuf,
+    },
+    /// Every entry has been worked through, or the job was stopped, nothing more will be sent
+    Finished {
+        /// What was done, in order, ready to be recorded in the journal
+        ops: Vec<FileOp>,
+        /// Why each entry that failed did
+        errors: Vec<FileNewerError>,
+    },
+}
+
+/// Works through a [`FileTask`] on a worker thread, reporting each entry as it starts on it.
+///
+/// Stopping the job lets the entry being worked on finish, then reports what was done.
+/// Dropping it stops it too, but what was done is never reported.
+pub struct FileJob {
+    job: Job<FileJobEvent>,
+}
+
+impl FileJob {
+    /// Starts on `task`
+    pub fn spawn(task: FileTask) -> Self {
+        let job = Job::spawn(|event| matches!(event, FileJobEvent::Finished { .. }), move |sender, cancelled| {
+            let paths = task.paths();
+            let mut ops = Vec::new();
+            let mut errors = Vec::new();
+            for (done, path) in paths.iter().enumerate() {
+                if cancelled.load(Ordering::Relaxed) { break; }
+                let progress = FileJobEvent::Progress { done, total: paths.len(), current: path.clone() };
+                // the receiver only goes away when the job is dropped
+                if sender.send(progress).is_err() { return; }
+                if let Err(e) = task.run_one(path, &mut ops) { errors.push(e); }
+            }
+            let _ = sender.send(FileJobEvent::Finished { ops, errors });
+        });
+        Self { job }
+    }
+
+    /// Everything the worker has sent since the last poll, never blocks
+    pub fn poll(&mut self) -> Vec<FileJobEvent> {
+        self.job.poll()
+    }
+
+    /// `true` once `Finished` has been polled
+    pub fn is_finished(&self) -> bool {
+        self.job.is_finished()
+    }
+
+    /// Stops the worker once the entry it is on is done, `Finished` still comes with what was done
+    pub fn stop(&self) {
+        self.job.stop();
+    }
+}
@@ -1,162 +1,313 @@
-use std::{env, io};
-use std::env::VarError;
+//! Reading entries and directories into [`FileInfo`]s, and resolving the paths users type.
+
 use std::ffi::{OsStr, OsString};
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 use std::time::SystemTime;
+use std::env;
+use std::env::VarError;
 use std::fs;
-use std::os::windows::prelude::MetadataExt;
-use chrono::{DateTime, Local};
+use std::io;
+use crate::dir_size::DirSize;
+use crate::error::FileNewerError;
+use crate::formatting::{DateFormat, SizeFormat};
+use crate::platform;
 
+/// What kind of entry something is, whether it may be written to is kept in [`FileInfo::can_be_written`]
 #[derive(PartialEq)]
+#[derive(Clone, Copy)]
 #[derive(Debug)]
 pub enum FileType{
-    WriteableDir,
-    UnwritableDir,
-    WritableFile,
-    UnwritableFile,
-    WritableLink,
-    UnwritableLink,
+    /// A regular file
+    File,
+    /// A directory
+    Dir,
+    /// A symlink whose target is there, or could not be checked
+    Link,
+    /// A symlink whose target does not exist
+    BrokenLink,
+    /// A named pipe
+    Fifo,
+    /// A unix domain socket
+    Socket,
+    /// A device read in blocks, like a disk
+    BlockDevice,
+    /// A device read a character at a time, like a terminal
+    CharDevice,
+    /// Anything the platform reports that is none of the above
     Unknown
 }
 
+impl FileType {
+    /// Maps a file type as reported by `symlink_metadata`, never returns `BrokenLink`
+    pub fn from_fs(file_type: &fs::FileType) -> FileType {
+        if file_type.is_symlink() { FileType::Link }
+        else if file_type.is_dir() { FileType::Dir }
+        else if file_type.is_file() { FileType::File }
+        else { platform::special_file_type(file_type).unwrap_or(FileType::Unknown) }
+    }
+}
+
+/// A single entry of a directory listing, as shown in one row of the explorer
 #[derive(Debug)]
 pub struct FileInfo {
-    pub(crate) file_type: FileType, //
-    pub(crate) can_be_written: bool,//
-    pub(crate) file_name: OsString,
-    pub(crate) file_ext: Option<String>,
-    pub(crate) file_size: u64,
-    pub(crate) last_access: Option<SystemTime>,
-    pub(crate) last_modification: Option<SystemTime>,
-    pub(crate) creation_time: Option<SystemTime>,
-    pub(crate) is_hidden: bool,
+    /// What kind of entry it is, a symlink is reported as a link rather than as its target
+    pub file_type: FileType,
+    /// Whether the entry, or for a link its target, is not read only
+    pub can_be_written: bool,
+    /// The name of the entry within its directory
+    pub file_name: OsString,
+    /// Extension without the leading `.`, `None` when missing or not UTF-8
+    pub file_ext: Option<String>,
+    /// Size in bytes as reported by the file system
+    pub file_size: u64,
+    /// When it was last read, `None` when the platform does not say
+    pub last_access: Option<SystemTime>,
+    /// When its contents last changed, `None` when the platform does not say
+    pub last_modification: Option<SystemTime>,
+    /// `None` where the platform or file system does not record a birth time
+    pub creation_time: Option<SystemTime>,
+    /// Hidden by the platform's convention, a leading `.` on Unix or the hidden attribute on Windows
+    pub is_hidden: bool,
+    /// Where a symlink points, exactly as stored in the link so it may be relative
+    pub link_target: Option<PathBuf>,
+    /// Unix `st_mode`, file type and permission bits, `None` on Windows
+    pub mode: Option<u32>,
+    /// Owner and group names, the numeric id when it has no name, `None` on Windows
+    pub owner: Option<String>,
+    /// See `owner`
+    pub group: Option<String>,
+    /// Recursive size of a directory, only known once it has been worked out in the background
+    pub dir_size: Option<DirSize>,
 }
 impl FileInfo{
+    /// `true` for directories, not for links to them
     pub fn is_dir(&self) -> bool{
-        self.file_type == FileType::UnwritableDir
-            || self.file_type == FileType::WriteableDir
+        self.file_type == FileType::Dir
     }
 
+    /// One letter summary of the type, upper case when the entry is read only
     pub fn single_char_desc(&self) -> &str{
-        match self.file_type {
-            FileType::UnwritableDir => { "D" }
-            FileType::WriteableDir => { "d" }
-            FileType::UnwritableLink => { "L" }
-            FileType::WritableLink => { "l" }
-            FileType::UnwritableFile => { "F" }
-            FileType::WritableFile => { "f" }
-            _ => { "?" }
-        }
+        let (writable, read_only) = match self.file_type {
+            FileType::Dir => { ("d", "D") }
+            FileType::Link => { ("l", "L") }
+            FileType::BrokenLink => { ("!", "!") }
+            FileType::File => { ("f", "F") }
+            FileType::Fifo => { ("p", "P") }
+            FileType::Socket => { ("s", "S") }
+            FileType::BlockDevice => { ("b", "B") }
+            FileType::CharDevice => { ("c", "C") }
+            FileType::Unknown => { ("?", "?") }
+        };
+        if self.can_be_written { writable } else { read_only }
     }
 
+    /// Human readable name of the type, ignoring write permissions
     pub fn type_to_basic_str(&self) -> &str{
         match self.file_type {
-            FileType::UnwritableDir => { "Directory" }
-            FileType::WriteableDir => { "Directory" }
-            FileType::UnwritableLink => { "Link" }
-            FileType::WritableLink => { "Link" }
-            FileType::UnwritableFile => { "File" }
-            FileType::WritableFile => { "File" }
-            _ => { "UNKNOWN" }
+            FileType::Dir => { "Directory" }
+            FileType::Link => { "Link" }
+            FileType::BrokenLink => { "Broken Link" }
+            FileType::File => { "File" }
+            FileType::Fifo => { "FIFO" }
+            FileType::Socket => { "Socket" }
+            FileType::BlockDevice => { "Block Device" }
+            FileType::CharDevice => { "Character Device" }
+            FileType::Unknown => { "UNKNOWN" }
         }
     }
 
-    pub fn gen_type_enum(is_dir:&bool, is_writable:&bool, is_link:&bool) -> FileType{
-        match (is_dir, is_writable, is_link) {
-            (true, false, false) => { FileType::UnwritableDir }
-            (true, true, false) => { FileType::WriteableDir }
-            (false, false, true) => { FileType::UnwritableLink }
-            (false, true, true) => { FileType::WritableLink }
-            (false, false, false) => { FileType::UnwritableFile }
-            (false, true, false) => { FileType::WritableFile }
-            _ => { FileType::Unknown }
-        }
+    /// Whether the size column means anything, directories and special files report no useful length
+    pub fn has_size(&self) -> bool {
+        matches!(self.file_type, FileType::File | FileType::Link | FileType::BrokenLink)
     }
 
+    /// The size shown and sorted on, the recursive total for directories once it is known
+    pub fn total_size(&self) -> Option<u64> {
+        if self.is_dir() { self.dir_size.map(|size| size.bytes) }
+        else if self.has_size() { Some(self.file_size) }
+        else { None }
+    }
 
-    pub fn last_access_formated(&self) -> String {format_system_time_opt(self.last_access)}
-    pub fn last_mod_formated(&self) -> String {format_system_time_opt(self.last_modification)}
-    pub fn creation_time_formated(&self) -> String {format_system_time_opt(self.creation_time)}
-}
+    /// The size as shown to the user, `-` when there is none to show
+    pub fn size_formated(&self, format: &SizeFormat) -> String {
+        self.total_size().map(|size| format.format(size)).unwrap_or_else(|| "-".to_owned())
+    }
 
-pub fn evaluate_path_vars(user_facing_path: &str) -> Result<String, VarError> {
-    let win_env_path: fn(&str) -> Result<String, VarError> = |path: &str| {
-        let mut path_str = env::var(path)?;
-        path_str.push_str("\\");
-        Ok(path_str)
-    };
-    let mut new_path =
-        user_facing_path.replace("/", "\\");
-    if new_path.starts_with("~")
-        { new_path = new_path.replacen("~", "%USERPROFILE%", 1) }
-    if new_path.starts_with("\\")
-        { new_path = new_path.replacen("\\", "%SYSTEMDRIVE%", 1) }
-
-    let mut final_path = if new_path.starts_with('%') {
-        let parts: Vec<&str> = new_path.split('%').collect();
-        if parts.len() < 2 { String::from(new_path) } else {
-            let mut path = win_env_path(parts[1])?;
-            for part in &parts[2..] { path.push_str(part); }
-            path
-        }
-    }else { String::from(new_path) };
+    /// `true` for symlinks, broken or not
+    pub fn is_link(&self) -> bool {
+        self.link_target.is_some()
+    }
+
+    /// The absolute location a symlink in `dir` points to, resolving relative targets against `dir`
+    pub fn resolve_link_target(&self, dir: &Path) -> Option<PathBuf> {
+        self.link_target.as_ref().map(|target| normalize_path(&dir.join(target)))
+    }
 
-    // Ensure the path ends with a \\
-    if !final_path.ends_with("\\") {
-        final_path.push_str("\\");
+    /// Permissions rendered like `ls -l`, `-` where the platform has no mode bits
+    pub fn permissions_formated(&self) -> String {
+        self.mode.map(format_mode).unwrap_or_else(|| "-".to_string())
     }
-    while final_path.contains("\\\\") {
-        final_path = final_path.replace("\\\\", "\\");
+
+    /// The name as a `&str`, failing rather than guessing when it is not valid UTF-8
+    pub fn name_str(&self) -> Result<&str, FileNewerError> {
+        self.file_name.to_str()
+            .ok_or_else(|| FileNewerError::NonUtf8Name(self.file_name.clone()))
     }
-    Ok(final_path)
+
+    /// `last_access` as shown to the user
+    pub fn last_access_formated(&self, format: &DateFormat) -> String {format.format(self.last_access)}
+    /// `last_modification` as shown to the user
+    pub fn last_mod_formated(&self, format: &DateFormat) -> String {format.format(self.last_modification)}
+    /// `creation_time` as shown to the user
+    pub fn creation_time_formated(&self, format: &DateFormat) -> String {format.format(self.creation_time)}
 }
 
-pub fn check_dir_exists(path: &String) -> bool { Path::is_dir(path.as_ref()) }
-
-pub fn get_files_in_dir<P: AsRef<Path>>(path: &P, inc_hidden: &bool) -> io::Result<Vec<FileInfo>> {
-    fs::read_dir(path)?.into_iter()
-        .filter_map(|entry| {
-            let dir = entry.ok()?;
-            let meta = dir.metadata().ok()?;
-            let file_ext = dir.path().extension()
-                .and_then(OsStr::to_str)
-                .map(|s| s.to_owned());
-            if !inc_hidden && is_hidden(&dir.path()).unwrap(){
-                return None
-            }
-            let file_type = FileInfo::gen_type_enum(&meta.is_dir(),
-                                                    &!meta.permissions().readonly(),
-                                                    &meta.file_type().is_symlink());
-            Some(Ok(FileInfo {
-                file_type,
-                can_be_written: !meta.permissions().readonly(),
-                file_name: dir.file_name(),
-                file_ext,
-                file_size: meta.len(),
-                last_access: meta.accessed().ok(),
-                last_modification: meta.modified().ok(),
-                creation_time: meta.created().ok(),
-                is_hidden: is_hidden(&dir.path()).unwrap()
-            }))
-        }).collect()
+/// Expands the home directory and environment variables in a path typed by the user.
+///
+/// On Unix `~`, `~user`, `$VAR` and `${VAR}` are expanded anywhere in the path, on Windows
+/// `~` and a leading `%VAR%` are. `.` and `..` are then resolved with [`normalize_path`].
+pub fn evaluate_path_vars(user_facing_path: &str) -> Result<PathBuf, FileNewerError> {
+    evaluate_path_vars_with(user_facing_path, &|name| env::var(name))
 }
 
-// code is_hidden from https://users.rust-lang.org/t/read-windows-hidden-file-attribute/51180/7
-pub fn is_hidden(file_path: &std::path::PathBuf) -> io::Result<bool> {
-    let metadata = fs::metadata(file_path)?;
-    let attributes = metadata.file_attributes();
+// evaluate_path_vars looking variables up with var, so tests need not change the environment
+pub(crate) fn evaluate_path_vars_with(user_facing_path: &str, var: &dyn Fn(&str) -> Result<String, VarError>)
+    -> Result<PathBuf, FileNewerError> {
+    let path = platform::evaluate_path_vars(user_facing_path, var)
+        .map_err(|source| FileNewerError::PathExpansion { path: user_facing_path.to_string(), source })?;
+    Ok(normalize_path(&path))
+}
 
-    if (attributes & 0x2) > 0 {
-        Ok(true)
-    } else {
-        Ok(false)
+/// Resolves `.` and `..` without touching the disk, `..` above the root is dropped like the shell does
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normal.components().next_back() {
+                Some(Component::Normal(_)) => { normal.pop(); }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normal.push(".."),
+            },
+            other => normal.push(other.as_os_str()),
+        }
     }
+    if normal.as_os_str().is_empty() { normal.push("."); }
+    normal
 }
 
-pub fn format_system_time_opt(sys_time: Option<SystemTime>) -> String{
-    sys_time.map(|t|
-        DateTime::<Local>::from(t)
-            .format("%F %T")
-            .to_string())
-        .unwrap_or_else(|| "XXXX-XX-XX XX:XX:XX".to_string())
-}
\ No newline at end of file
+/// `true` when `path` exists and is a directory
+pub fn check_dir_exists(path: &Path) -> bool { path.is_dir() }
+
+/// The result of listing a directory, entries that could not be read are kept in `errors`
+#[derive(Debug, Default)]
+pub struct DirListing {
+    /// The entries read, in the order the file system returned them
+    pub files: Vec<FileInfo>,
+    /// Why each entry that could not be read was left out
+    pub errors: Vec<FileNewerError>,
+}
+
+/// Lists every entry of `path`, leaving out hidden ones unless `inc_hidden` is set.
+///
+/// Only failing to open `path` itself is an error, entries that disappear or can not be
+/// read while listing are collected into [`DirListing::errors`] instead.
+pub fn get_files_in_dir<P: AsRef<Path>>(path: &P, inc_hidden: &bool) -> Result<DirListing, FileNewerError> {
+    let mut listing = DirListing::default();
+    for entry in read_dir_entries(path.as_ref(), *inc_hidden)? {
+        match entry {
+            Ok(file) => listing.files.push(file),
+            Err(e) => listing.errors.push(e),
+        }
+    }
+    Ok(listing)
+}
+
+// lazily reads the entries of path one at a time, so callers can stop part way through
+pub(crate) fn read_dir_entries(path: &Path, inc_hidden: bool)
+    -> Result<impl Iterator<Item = Result<FileInfo, FileNewerError>> + '_, FileNewerError> {
+    let entries = fs::read_dir(path).map_err(|e| FileNewerError::from_io(path, e))?;
+    Ok(entries
+        .map(move |entry| entry.map_err(|e| FileNewerError::from_io(path, e))
+            .and_then(|dir| get_file_info(&dir.path())))
+        .filter(move |entry| inc_hidden || !matches!(entry, Ok(file) if file.is_hidden)))
+}
+
+/// Reads the entry at `path` without following it if it is a symlink
+pub fn get_file_info(path: &Path) -> Result<FileInfo, FileNewerError> {
+    // symlink_metadata so links are reported as links rather than as what they point to
+    let meta = fs::symlink_metadata(path).map_err(|e| FileNewerError::from_entry_io(path, e))?;
+    file_info_from(path, meta)
+}
+
+// builds the FileInfo for path from metadata already read without following links
+pub(crate) fn file_info_from(path: &Path, meta: fs::Metadata) -> Result<FileInfo, FileNewerError> {
+    let file_name = path.file_name()
+        .map(OsStr::to_os_string)
+        .unwrap_or_else(|| path.as_os_str().to_os_string());
+    let file_ext = path.extension()
+        .and_then(OsStr::to_str)
+        .map(|s| s.to_owned());
+    let is_link = meta.file_type().is_symlink();
+    let link_target = if is_link {
+        Some(fs::read_link(path).map_err(|e| FileNewerError::from_entry_io(path, e))?)
+    } else { None };
+    // a link is only as writable as whatever it points at
+    let target = if is_link { Some(fs::metadata(path)) } else { None };
+    let target_meta = target.as_ref().and_then(|target| target.as_ref().ok());
+    let can_be_written = !target_meta.unwrap_or(&meta).permissions().readonly();
+    // only a missing or looping target makes a link broken, one that can not be read right now is still a link
+    let file_type = match target {
+        Some(Err(e)) if is_dangling(&e) => { FileType::BrokenLink }
+        _ => { FileType::from_fs(&meta.file_type()) }
+    };
+    let (owner, group) = platform::ownership(&meta);
+    Ok(FileInfo {
+        file_type,
+        can_be_written,
+        file_name,
+        file_ext,
+        file_size: meta.len(),
+        last_access: meta.accessed().ok(),
+        last_modification: meta.modified().ok(),
+        creation_time: platform::creation_time(&meta),
+        is_hidden: platform::is_hidden(path, &meta),
+        link_target,
+        mode: platform::mode(&meta),
+        owner,
+        group,
+        dir_size: None,
+    })
+}
+
+fn is_dangling(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::NotADirectory) || platform::is_link_loop(e)
+}
+
+/// Renders unix mode bits the way `ls -l` does, e.g. `drwxr-xr-x` or `-rwsr-x--T`
+pub fn format_mode(mode: u32) -> String {
+    let kind = match mode & 0o170000 {
+        0o040000 => 'd',
+        0o120000 => 'l',
+        0o010000 => 'p',
+        0o140000 => 's',
+        0o060000 => 'b',
+        0o020000 => 'c',
+        _ => '-',
+    };
+    let bit = |mask: u32, c: char| if mode & mask != 0 { c } else { '-' };
+    // the execute slot doubles up for setuid, setgid and sticky, upper case when not also executable
+    let exec = |mask: u32, special_mask: u32, special: char| {
+        match (mode & mask != 0, mode & special_mask != 0) {
+            (true, true) => special,
+            (false, true) => special.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        }
+    };
+    [kind,
+        bit(0o400, 'r'), bit(0o200, 'w'), exec(0o100, 0o4000, 's'),
+        bit(0o040, 'r'), bit(0o020, 'w'), exec(0o010, 0o2000, 's'),
+        bit(0o004, 'r'), bit(0o002, 'w'), exec(0o001, 0o1000, 't'),
+    ].iter().collect()
+}
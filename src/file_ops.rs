@@ -0,0 +1,208 @@
+//! Copying, moving, renaming, deleting and creating entries.
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::error::FileNewerError;
+use crate::file_manager::{read_dir_entries, FileType};
+use crate::file_ordering::NameOrder;
+use crate::platform;
+use crate::walk::Walk;
+
+/// What to do when something of the same name is already where an entry is being copied or moved
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum ConflictPolicy {
+    /// Delete what is already there first
+    Overwrite,
+    /// Leave both where they are and carry on with the next entry
+    Skip,
+    /// Give the new entry a free name such as `notes (2).txt`
+    KeepBoth,
+}
+
+/// The entries of `sources` that already have a namesake in `dest_dir`
+pub fn conflicts(sources: &[PathBuf], dest_dir: &Path) -> Vec<PathBuf> {
+    sources.iter()
+        .filter(|src| src.file_name()
+            .map(|name| dest_dir.join(name))
+            .is_some_and(|dest| dest != **src && fs::symlink_metadata(dest).is_ok()))
+        .cloned()
+        .collect()
+}
+
+/// Copies `src` into `dest_dir`, everything inside it too when it is a directory.
+///
+/// Symlinks are copied as links rather than followed. Returns where the copy ended up, or
+/// `None` when it was skipped because of a conflict.
+pub fn copy_entry(src: &Path, dest_dir: &Path, policy: ConflictPolicy) -> Result<Option<PathBuf>, FileNewerError> {
+    check_not_into_itself(src, dest_dir)?;
+    // copying onto itself can only ever mean making a second copy
+    let policy = if src.parent() == Some(dest_dir) { ConflictPolicy::KeepBoth } else { policy };
+    let Some(dest) = resolve_conflict(src, dest_dir, policy)? else { return Ok(None) };
+    copy_recursive(src, &dest)?;
+    Ok(Some(dest))
+}
+
+/// Moves `src` into `dest_dir`, falling back to copy and delete when it is on another file system.
+///
+/// Returns where the entry ended up, or `None` when it was skipped or already in `dest_dir`.
+pub fn move_entry(src: &Path, dest_dir: &Path, policy: ConflictPolicy) -> Result<Option<PathBuf>, FileNewerError> {
+    if src.parent() == Some(dest_dir) { return Ok(None); }
+    check_not_into_itself(src, dest_dir)?;
+    let Some(dest) = resolve_conflict(src, dest_dir, policy)? else { return Ok(None) };
+    move_path(src, &dest)?;
+    Ok(Some(dest))
+}
+
+/// Gives `path` a new name in the same folder, never replacing anything already called that
+pub fn rename_entry(path: &Path, new_name: &str) -> Result<PathBuf, FileNewerError> {
+    check_name(new_name)?;
+    let dest = path.with_file_name(new_name);
+    if dest == path { return Ok(dest); }
+    if fs::symlink_metadata(&dest).is_ok() { return Err(FileNewerError::AlreadyExists(dest)); }
+    fs::rename(path, &dest).map_err(|e| FileNewerError::from_io(path, e))?;
+    Ok(dest)
+}
+
+/// Permanently deletes `path`, everything inside it too when it is a directory, links are not followed
+pub fn delete_entry(path: &Path) -> Result<(), FileNewerError> {
+    let meta = fs::symlink_metadata(path).map_err(|e| FileNewerError::from_io(path, e))?;
+    let removed = if meta.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+    removed.map_err(|e| FileNewerError::from_io(path, e))
+}
+
+/// Makes an empty folder called `name` in `dir`, or `name (2)` and so on when that is taken
+pub fn create_dir(dir: &Path, name: &str) -> Result<PathBuf, FileNewerError> {
+    check_name(name)?;
+    let path = free_name(&dir.join(name));
+    fs::create_dir(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+    Ok(path)
+}
+
+/// Makes an empty file called `name` in `dir`, or `name (2)` and so on when that is taken
+pub fn create_file(dir: &Path, name: &str) -> Result<PathBuf, FileNewerError> {
+    check_name(name)?;
+    let path = free_name(&dir.join(name));
+    OpenOptions::new().write(true).create_new(true).open(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+    Ok(path)
+}
+
+/// The folder new entries can be made from copies of, `~/Templates` unless set otherwise
+pub fn templates_dir() -> Option<PathBuf> {
+    platform::templates_dir()
+}
+
+/// The files and folders in `dir`, usually [`templates_dir`], sorted by name, hidden ones left out
+pub fn list_templates(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = read_dir_entries(dir, false) else { return Vec::new() };
+    let mut templates: Vec<_> = entries.filter_map(Result::ok)
+        .filter(|file| matches!(file.file_type, FileType::File | FileType::Dir))
+        .map(|file| file.file_name)
+        .collect();
+    let order = NameOrder::default();
+    templates.sort_by(|a, b| order.compare(a, b));
+    templates.into_iter().map(|name| dir.join(name)).collect()
+}
+
+/// `false` for names that are empty, `.`/`..` or would reach into another folder
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".."
+        && !name.contains(['/', '\0']) && !(cfg!(windows) && name.contains('\\'))
+}
+
+// renames src to dest, copying then deleting when they are on different file systems
+pub(crate) fn move_path(src: &Path, dest: &Path) -> Result<(), FileNewerError> {
+    match fs::rename(src, dest) {
+        Ok(()) => { Ok(()) }
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_recursive(src, dest)?;
+            delete_entry(src)
+        }
+        Err(e) => { Err(FileNewerError::from_io(src, e)) }
+    }
+}
+
+fn check_name(name: &str) -> Result<(), FileNewerError> {
+    if is_valid_name(name) { Ok(()) } else { Err(FileNewerError::InvalidName(name.to_string())) }
+}
+
+/// The first of `path`, `name (2).ext`, `name (3).ext`... that does not exist yet
+pub fn free_name(path: &Path) -> PathBuf {
+    if fs::symlink_metadata(path).is_err() { return path.to_path_buf(); }
+    let stem = path.file_stem().unwrap_or_default().to_os_string();
+    let ext = path.extension();
+    (2..).map(|n| {
+        let mut name = stem.clone();
+        name.push(format!(" ({})", n));
+        if let Some(ext) = ext { name.push("."); name.push(ext); }
+        path.with_file_name(name)
+    })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+// a directory can not be copied or moved into itself or anything below it
+fn check_not_into_itself(src: &Path, dest_dir: &Path) -> Result<(), FileNewerError> {
+    let is_dir = fs::symlink_metadata(src).map_err(|e| FileNewerError::from_io(src, e))?.is_dir();
+    let (Ok(src), Ok(dest_dir)) = (fs::canonicalize(src), fs::canonicalize(dest_dir)) else { return Ok(()) };
+    if is_dir && dest_dir.starts_with(&src) { Err(FileNewerError::IntoItself(src)) } else { Ok(()) }
+}
+
+// where src should go in dest_dir under policy, None to skip it
+fn resolve_conflict(src: &Path, dest_dir: &Path, policy: ConflictPolicy) -> Result<Option<PathBuf>, FileNewerError> {
+    let name: OsString = src.file_name()
+        .ok_or_else(|| FileNewerError::InvalidName(src.to_string_lossy().into_owned()))?
+        .to_os_string();
+    let dest = dest_dir.join(name);
+    if fs::symlink_metadata(&dest).is_err() { return Ok(Some(dest)); }
+    match policy {
+        ConflictPolicy::Overwrite => {
+            // deleting what is in the way would take src with it
+            if src.starts_with(&dest) { return Err(FileNewerError::AlreadyExists(dest)); }
+            delete_entry(&dest)?;
+            Ok(Some(dest))
+        }
+        ConflictPolicy::Skip => { Ok(None) }
+        ConflictPolicy::KeepBoth => { Ok(Some(free_name(&dest))) }
+    }
+}
+
+pub(crate) fn copy_recursive(src: &Path, dest: &Path) -> Result<(), FileNewerError> {
+    let meta = fs::symlink_metadata(src).map_err(|e| FileNewerError::from_io(src, e))?;
+    copy_single(src, dest, &meta)?;
+    if !meta.is_dir() { return Ok(()); }
+    // permissions are put on directories last, so read only ones can still be filled
+    let mut dir_permissions = vec![(dest.to_path_buf(), meta.permissions())];
+    for entry in Walk::new(src, true)? {
+        let entry = entry?;
+        let dest = dest.join(&entry.relative_path);
+        copy_single(&entry.path, &dest, &entry.meta)?;
+        if entry.meta.is_dir() { dir_permissions.push((dest, entry.meta.permissions())); }
+    }
+    for (dir, permissions) in dir_permissions.into_iter().rev() {
+        fs::set_permissions(&dir, permissions).map_err(|e| FileNewerError::from_io(&dir, e))?;
+    }
+    Ok(())
+}
+
+// copies one entry, a directory is made empty for what is inside it to be copied into
+fn copy_single(src: &Path, dest: &Path, meta: &fs::Metadata) -> Result<(), FileNewerError> {
+    let file_type = meta.file_type();
+    if file_type.is_symlink() {
+        let target = fs::read_link(src).map_err(|e| FileNewerError::from_io(src, e))?;
+        platform::symlink(&target, dest, src.is_dir()).map_err(|e| FileNewerError::from_io(dest, e))?;
+    } else if file_type.is_dir() {
+        fs::create_dir(dest).map_err(|e| FileNewerError::from_io(dest, e))?;
+    } else if file_type.is_file() {
+        fs::copy(src, dest).map_err(|e| FileNewerError::from_io(src, e))?;
+    } else {
+        return Err(FileNewerError::Io {
+            path: src.to_path_buf(), source: io::Error::other("only files, folders and links can be copied"),
+        });
+    }
+    Ok(())
+}
@@ -1,32 +1,177 @@
-use crate::file_manager::FileInfo;
+//! What a listing shows and the order it is shown in.
 
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ffi::OsStr;
+use std::path::Path;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use crate::columns::Column;
+use crate::error::FileNewerError;
+use crate::file_manager::{DirListing, FileInfo, get_files_in_dir};
+use crate::formatting::{DateFormat, SizeFormat};
+
+/// The column a listing is ordered by, `Nan` keeps the order the file system returned
 #[derive(PartialEq)]
 #[derive(Clone)]
-pub(crate) enum SortBy {
+#[derive(Debug)]
+pub enum SortBy {
+    /// By name, as set out by [`NameOrder`]
     Name,
+    /// By kind of entry
     Type,
+    /// By extension, entries without one first
     Ext,
+    /// By creation time
     CreateDate,
+    /// By modification time
     ModDate,
+    /// By access time
     ViewDate,
+    /// By size, directories by their recursive size once known
     Size,
+    /// By mode bits
+    Permissions,
+    /// By owner name
+    Owner,
+    /// By group name
+    Group,
+    /// Not sorted at all
     Nan,
 }
 
-pub(crate) struct DisplayOptions{
-    pub(crate) show_file_type:bool,
-    pub(crate) show_hidden:bool,
-    pub(crate) show_file_ext: bool,
-    pub(crate) show_file_size: bool,
-    pub(crate) show_last_acc: bool,
-    pub(crate) show_last_mod: bool,
-    pub(crate) show_creation: bool,
-    pub(crate) sort_by: SortBy,
-    pub(crate) filter_dec:bool,
+/// A further column to order by when entries tie on everything before it
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct SortKey {
+    /// The column compared
+    pub by: SortBy,
+    /// Largest first rather than smallest first
+    pub descending: bool,
 }
 
-impl DisplayOptions{
-    pub(crate) fn default() -> Self{
+/// How upper and lower case letters are told apart when comparing names
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum CaseOrder {
+    /// `Zeta` before `alpha`, like the raw bytes
+    Sensitive,
+    /// Only `A`-`Z` match their lower case letters
+    AsciiInsensitive,
+    /// Every script is lower cased, so `Ärger` and `ärger` sit together
+    Folded,
+}
+
+/// How names are compared when sorting by [`SortBy::Name`]
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct NameOrder {
+    /// Runs of digits compare by value, so `file2` comes before `file10`
+    pub natural: bool,
+    /// How upper and lower case compare
+    pub case: CaseOrder,
+    /// `é` sorts the same as `e`
+    pub ignore_accents: bool,
+}
+
+impl Default for NameOrder {
+    fn default() -> Self {
+        Self { natural: true, case: CaseOrder::Folded, ignore_accents: false }
+    }
+}
+
+impl NameOrder {
+    /// Compares two names, names that only differ in ways being ignored fall back to their raw bytes
+    pub fn compare(&self, a: &OsStr, b: &OsStr) -> Ordering {
+        let (key_a, key_b) = (self.key(a), self.key(b));
+        let ord = if self.natural { natural_cmp(&key_a, &key_b) } else { key_a.cmp(&key_b) };
+        ord.then_with(|| a.cmp(b))
+    }
+
+    // the name with everything being ignored taken out, only allocates when something is
+    fn key<'a>(&self, name: &'a OsStr) -> Cow<'a, str> {
+        let mut key = name.to_string_lossy();
+        if self.ignore_accents {
+            key = Cow::Owned(key.nfd().filter(|c| !is_combining_mark(*c)).collect());
+        }
+        match self.case {
+            CaseOrder::Sensitive => { key }
+            CaseOrder::AsciiInsensitive => { Cow::Owned(key.to_ascii_lowercase()) }
+            CaseOrder::Folded => { Cow::Owned(key.to_lowercase()) }
+        }
+    }
+}
+
+// compares digit runs by their value and everything else char by char
+fn natural_cmp(mut a: &str, mut b: &str) -> Ordering {
+    while let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) {
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (num_a, rest_a) = split_digits(a);
+            let (num_b, rest_b) = split_digits(b);
+            // compared as text so any number of digits works without overflowing
+            let (num_a, num_b) = (num_a.trim_start_matches('0'), num_b.trim_start_matches('0'));
+            let ord = num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b));
+            if ord.is_ne() { return ord; }
+            (a, b) = (rest_a, rest_b);
+        } else {
+            if x != y { return x.cmp(&y); }
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+        }
+    }
+    a.cmp(b)
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+/// Which entries and columns to show and how to order them
+pub struct DisplayOptions{
+    /// Show the [`Column::Type`] column
+    pub show_file_type:bool,
+    /// List hidden entries
+    pub show_hidden:bool,
+    /// Show the [`Column::Ext`] column
+    pub show_file_ext: bool,
+    /// Show the [`Column::Size`] column
+    pub show_file_size: bool,
+    /// Show the [`Column::Accessed`] column
+    pub show_last_acc: bool,
+    /// Show the [`Column::Modified`] column
+    pub show_last_mod: bool,
+    /// Show the [`Column::Created`] column
+    pub show_creation: bool,
+    /// Show the [`Column::Permissions`] column
+    pub show_permissions: bool,
+    /// Show the [`Column::Owner`] column
+    pub show_owner: bool,
+    /// Show the [`Column::Group`] column
+    pub show_group: bool,
+    /// Every column in the order they appear, whether each is shown is down to the `show_` flags
+    pub column_order: Vec<Column>,
+    /// Work out recursive directory sizes in the background
+    pub calc_dir_sizes: bool,
+    /// How sizes are written out
+    pub size_format: SizeFormat,
+    /// How dates are written out
+    pub date_format: DateFormat,
+    /// The main column to order by
+    pub sort_by: SortBy,
+    /// How names compare, for sorting by name
+    pub name_order: NameOrder,
+    /// Sort descending rather than ascending
+    pub filter_dec:bool,
+    /// Tie breakers applied in order after `sort_by`
+    pub then_by: Vec<SortKey>,
+    /// Keep directories above everything else, whatever they are sorted by
+    pub dirs_first: bool,
+}
+
+impl Default for DisplayOptions{
+    fn default() -> Self{
         Self {
             show_file_type: true,
             show_hidden: false,
@@ -35,38 +180,182 @@ impl DisplayOptions{
             show_last_acc: false,
             show_last_mod: true,
             show_creation: true,
+            // only unix has anything to put in these columns
+            show_permissions: cfg!(unix),
+            show_owner: cfg!(unix),
+            show_group: cfg!(unix),
+            column_order: Column::ALL.to_vec(),
+            calc_dir_sizes: false,
+            size_format: SizeFormat::default(),
+            date_format: DateFormat::default(),
             sort_by: SortBy::Nan,
+            name_order: NameOrder::default(),
             filter_dec: false,
+            then_by: Vec::new(),
+            dirs_first: false,
         }
     }
+}
 
-    pub(crate) fn sort(&self, file_info: &mut Vec<FileInfo> ) {
-        if self.sort_by == SortBy::Nan { return; }
+impl DisplayOptions{
+    /// Lists `path` honouring `show_hidden` and returns the entries already sorted
+    pub fn list<P: AsRef<Path>>(&self, path: &P) -> Result<DirListing, FileNewerError> {
+        let mut listing = get_files_in_dir(path, &self.show_hidden)?;
+        self.sort(&mut listing.files);
+        Ok(listing)
+    }
+
+    /// Orders `file_info` in place by `sort_by` then each of `then_by`, keeping ties in the order they came.
+    ///
+    /// Entries missing the value being sorted on, like an unknown creation time, always go last.
+    pub fn sort(&self, file_info: &mut [FileInfo] ) {
+        if self.sort_by == SortBy::Nan && self.then_by.is_empty() && !self.dirs_first { return; }
+        file_info.sort_by(|a, b| self.compare(a, b));
+    }
+
+    /// The options kept between runs, as `key=value` lines for [`apply_settings`](Self::apply_settings)
+    pub fn settings(&self) -> String {
+        let mut lines: Vec<String> = self.flags().iter().map(|(key, on)| format!("{}={}", key, on)).collect();
+        let columns: Vec<&str> = self.column_order.iter().map(Column::key).collect();
+        lines.push(format!("columns={}", columns.join(",")));
+        lines.join("\n")
+    }
 
-        for i in 1..file_info.len(){
-            let mut j = i;
-            while j > 0 && self.should_swap(&file_info[j-1], &file_info[j]) {
-                file_info.swap(j, j-1);
-                j = j-1
+    /// Reads back what [`settings`](Self::settings) wrote, skipping anything it does not recognise
+    pub fn apply_settings(&mut self, settings: &str) {
+        for (key, value) in settings.lines().filter_map(|line| line.split_once('=')) {
+            if key == "columns" {
+                let mut order: Vec<Column> = Vec::new();
+                // columns added since the settings were kept go at the end
+                for column in value.split(',').filter_map(Column::from_key).chain(Column::ALL) {
+                    if !order.contains(&column) { order.push(column); }
+                }
+                self.column_order = order;
+            } else if let (Some((_, flag)), Ok(on)) = (self.flags_mut().into_iter().find(|(name, _)| *name == key), value.parse()) {
+                *flag = on;
             }
         }
     }
-    fn should_swap(&self, a:&FileInfo, b:&FileInfo) -> bool{
-        match self.sort_by{
-            SortBy::Name => { self.cmp(&a.file_name, &b.file_name) }
-            SortBy::Ext => { self.cmp(&a.file_ext, &b.file_ext) }
-            SortBy::CreateDate => { self.cmp(&a.creation_time, &b.creation_time) }
-            SortBy::ModDate => { self.cmp(&a.last_modification, &b.last_modification) }
-            SortBy::Size => { self.cmp(&a.file_size, &b.file_size) }
-            SortBy::Type => { self.cmp(&a.single_char_desc(), &b.single_char_desc()) }
-            SortBy::ViewDate => { self.cmp(&a.last_access, &b.last_access)}
-            SortBy::Nan => {false}
+
+    // the on / off options kept by settings, with the keys they are kept under
+    fn flags(&self) -> [(&'static str, bool); 12] {
+        [("show_file_type", self.show_file_type), ("show_hidden", self.show_hidden),
+         ("show_file_ext", self.show_file_ext), ("show_file_size", self.show_file_size),
+         ("show_last_acc", self.show_last_acc), ("show_last_mod", self.show_last_mod),
+         ("show_creation", self.show_creation), ("show_permissions", self.show_permissions),
+         ("show_owner", self.show_owner), ("show_group", self.show_group),
+         ("calc_dir_sizes", self.calc_dir_sizes), ("dirs_first", self.dirs_first)]
+    }
+
+    fn flags_mut(&mut self) -> [(&'static str, &mut bool); 12] {
+        [("show_file_type", &mut self.show_file_type), ("show_hidden", &mut self.show_hidden),
+         ("show_file_ext", &mut self.show_file_ext), ("show_file_size", &mut self.show_file_size),
+         ("show_last_acc", &mut self.show_last_acc), ("show_last_mod", &mut self.show_last_mod),
+         ("show_creation", &mut self.show_creation), ("show_permissions", &mut self.show_permissions),
+         ("show_owner", &mut self.show_owner), ("show_group", &mut self.show_group),
+         ("calc_dir_sizes", &mut self.calc_dir_sizes), ("dirs_first", &mut self.dirs_first)]
+    }
+
+    /// Whether `by` is `sort_by` or any of `then_by`
+    pub fn sorts_by(&self, by: &SortBy) -> bool {
+        self.sort_by == *by || self.then_by.iter().any(|key| key.by == *by)
+    }
+
+    /// Whether `column` is switched on, the name is always shown
+    pub fn shows(&self, column: Column) -> bool {
+        match column {
+            Column::Type => { self.show_file_type }
+            Column::Name => { true }
+            Column::Ext => { self.show_file_ext }
+            Column::Created => { self.show_creation }
+            Column::Modified => { self.show_last_mod }
+            Column::Accessed => { self.show_last_acc }
+            Column::Size => { self.show_file_size }
+            Column::Permissions => { self.show_permissions }
+            Column::Owner => { self.show_owner }
+            Column::Group => { self.show_group }
         }
     }
 
-    fn cmp<T: PartialOrd>(&self, a:&T, b:&T) -> bool{
-        if self.filter_dec { a < b } else { a > b }
+    /// The columns to show, left to right
+    pub fn columns(&self) -> Vec<Column> {
+        self.column_order.iter().copied().filter(|col| self.shows(*col)).collect()
     }
 
+    /// Moves `column` into the place of `target`, shifting everything between them along one
+    pub fn move_column(&mut self, column: Column, target: Column) {
+        let (Some(from), Some(to)) = (self.column_order.iter().position(|col| *col == column),
+                                      self.column_order.iter().position(|col| *col == target))
+            else { return };
+        let column = self.column_order.remove(from);
+        self.column_order.insert(to, column);
+    }
+
+    /// Sorts by `by` the way clicking a column header does.
+    ///
+    /// Clicking the column already sorted on flips its direction, any other column replaces the
+    /// whole order. With `secondary` the column is added as a tie breaker instead, or flipped if
+    /// it is already one.
+    pub fn toggle_sort(&mut self, by: SortBy, secondary: bool) {
+        if self.sort_by == by { self.filter_dec = !self.filter_dec; }
+        else if !secondary || self.sort_by == SortBy::Nan {
+            self.sort_by = by;
+            self.filter_dec = false;
+            self.then_by.clear();
+        }
+        else if let Some(key) = self.then_by.iter_mut().find(|key| key.by == by) {
+            key.descending = !key.descending;
+        }
+        else { self.then_by.push(SortKey { by, descending: false }); }
+    }
+
+    /// Where `by` comes in the order, `0` being `sort_by`, and whether it is descending
+    pub fn sort_position(&self, by: &SortBy) -> Option<(usize, bool)> {
+        if *by == SortBy::Nan { return None; }
+        if self.sort_by == *by { return Some((0, self.filter_dec)); }
+        self.then_by.iter().position(|key| key.by == *by)
+            .map(|idx| (idx + 1, self.then_by[idx].descending))
+    }
+
+    /// The order `sort` puts two entries in
+    pub fn compare(&self, a:&FileInfo, b:&FileInfo) -> Ordering {
+        let dirs = if self.dirs_first { b.is_dir().cmp(&a.is_dir()) } else { Ordering::Equal };
+        dirs.then_with(|| self.compare_by(&self.sort_by, self.filter_dec, a, b))
+            .then_with(|| self.then_by.iter()
+                .map(|key| self.compare_by(&key.by, key.descending, a, b))
+                .find(|ord| ord.is_ne())
+                .unwrap_or(Ordering::Equal))
+    }
+
+    fn compare_by(&self, sort_by: &SortBy, descending: bool, a:&FileInfo, b:&FileInfo) -> Ordering {
+        match sort_by {
+            SortBy::Name => { directed(self.name_order.compare(&a.file_name, &b.file_name), descending) }
+            SortBy::Ext => { missing_last(&a.file_ext, &b.file_ext, descending) }
+            SortBy::CreateDate => { missing_last(&a.creation_time, &b.creation_time, descending) }
+            SortBy::ModDate => { missing_last(&a.last_modification, &b.last_modification, descending) }
+            SortBy::Size => { missing_last(&a.total_size(), &b.total_size(), descending) }
+            SortBy::Type => { directed(a.single_char_desc().cmp(b.single_char_desc()), descending) }
+            SortBy::ViewDate => { missing_last(&a.last_access, &b.last_access, descending) }
+            SortBy::Permissions => {
+                missing_last(&a.mode.map(|m| m & 0o7777), &b.mode.map(|m| m & 0o7777), descending)
+            }
+            SortBy::Owner => { missing_last(&a.owner, &b.owner, descending) }
+            SortBy::Group => { missing_last(&a.group, &b.group, descending) }
+            SortBy::Nan => { Ordering::Equal }
+        }
+    }
 }
 
+fn directed(ord: Ordering, descending: bool) -> Ordering {
+    if descending { ord.reverse() } else { ord }
+}
+
+// unlike Option's own Ord, None goes last whichever direction the rest is sorted in
+fn missing_last<T: Ord>(a: &Option<T>, b: &Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => { directed(a.cmp(b), descending) }
+        (Some(_), None) => { Ordering::Less }
+        (None, Some(_)) => { Ordering::Greater }
+        (None, None) => { Ordering::Equal }
+    }
+}
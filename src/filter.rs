@@ -0,0 +1,142 @@
+//! Narrowing a listing down to the entries whose names match a pattern.
+
+use regex::{Regex, RegexBuilder};
+use crate::error::FileNewerError;
+use crate::file_manager::FileInfo;
+
+/// How the text typed into a filter is read
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum MatchMode {
+    /// The name contains the text anywhere
+    Substring,
+    /// The whole name matches a shell pattern such as `*.rs` or `file?.[ch]`
+    Glob,
+    /// The name contains a match for a regular expression
+    Regex,
+    /// The name contains every character of the text in order, so `flmg` finds `file_manager.rs`
+    Fuzzy,
+}
+
+/// Which kinds of entry a filter lets through
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum TypeFilter {
+    /// Every entry
+    All,
+    /// Everything that is not a directory
+    FilesOnly,
+    /// Only directories
+    DirsOnly,
+}
+
+/// What to narrow a listing down to, compile it with [`NameFilter::compile`] before matching
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct NameFilter {
+    /// What names are matched against, an empty pattern matches every name
+    pub pattern: String,
+    /// How `pattern` is read
+    pub mode: MatchMode,
+    /// Tell upper and lower case apart, off by default
+    pub case_sensitive: bool,
+    /// Which kinds of entry are let through at all
+    pub types: TypeFilter,
+}
+
+impl Default for NameFilter {
+    fn default() -> Self {
+        Self { pattern: String::new(), mode: MatchMode::Substring, case_sensitive: false, types: TypeFilter::All }
+    }
+}
+
+impl NameFilter {
+    /// `false` when the filter would let everything through
+    pub fn is_active(&self) -> bool {
+        !self.pattern.is_empty() || self.types != TypeFilter::All
+    }
+
+    /// Turns the pattern into something that can be matched against, failing on a bad regex
+    pub fn compile(&self) -> Result<NameMatcher, FileNewerError> {
+        let regex = if self.pattern.is_empty() { None } else {
+            let source = match self.mode {
+                MatchMode::Substring => { regex::escape(&self.pattern) }
+                MatchMode::Glob => { glob_to_regex(&self.pattern) }
+                MatchMode::Regex => { self.pattern.clone() }
+                MatchMode::Fuzzy => {
+                    self.pattern.chars().map(|c| regex::escape(c.encode_utf8(&mut [0; 4])))
+                        .collect::<Vec<_>>().join(".*")
+                }
+            };
+            Some(RegexBuilder::new(&source)
+                .case_insensitive(!self.case_sensitive)
+                .build()
+                .map_err(|source| FileNewerError::InvalidPattern { pattern: self.pattern.clone(), source })?)
+        };
+        Ok(NameMatcher { regex, types: self.types })
+    }
+}
+
+/// A compiled [`NameFilter`]
+#[derive(Debug)]
+pub struct NameMatcher {
+    // None matches every name
+    regex: Option<Regex>,
+    types: TypeFilter,
+}
+
+impl NameMatcher {
+    /// `true` when both the type and the name of `file` match
+    pub fn matches(&self, file: &FileInfo) -> bool {
+        let type_matches = match self.types {
+            TypeFilter::All => { true }
+            TypeFilter::FilesOnly => { !file.is_dir() }
+            TypeFilter::DirsOnly => { file.is_dir() }
+        };
+        type_matches && self.matches_name(&file.file_name.to_string_lossy())
+    }
+
+    /// `true` when `name` matches the pattern, whatever its type
+    pub fn matches_name(&self, name: &str) -> bool {
+        self.regex.as_ref().is_none_or(|regex| regex.is_match(name))
+    }
+}
+
+// `*` any run of characters, `?` any one, `[...]` and `[!...]` a set, everything else literal
+fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => { regex.push_str(".*") }
+            '?' => { regex.push('.') }
+            '[' => {
+                let negated = chars.get(i + 1) == Some(&'!');
+                let start = if negated { i + 2 } else { i + 1 };
+                // a `]` straight after the opening bracket is part of the set
+                let close = chars.iter().skip(start + 1).position(|c| *c == ']').map(|pos| pos + start + 1);
+                match close {
+                    Some(close) => {
+                        regex.push_str(if negated { "[^" } else { "[" });
+                        for c in &chars[start..close] {
+                            if matches!(c, '\\' | '[' | ']' | '^' | '&' | '~') { regex.push('\\'); }
+                            regex.push(*c);
+                        }
+                        regex.push(']');
+                        i = close;
+                    }
+                    // an unclosed bracket is just text, like in the shell
+                    None => { regex.push_str("\\[") }
+                }
+            }
+            c => { regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))) }
+        }
+        i += 1;
+    }
+    regex.push('$');
+    regex
+}
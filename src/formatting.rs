@@ -0,0 +1,146 @@
+//! Turning sizes and timestamps into text.
+
+use std::time::SystemTime;
+use chrono::{DateTime, Local, Utc};
+use chrono::format::{Item, StrftimeItems};
+
+/// The units file sizes are shown in
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum SizeUnits {
+    /// The exact number of bytes
+    Bytes,
+    /// Powers of 1000, kB, MB, GB...
+    Si,
+    /// Powers of 1024, KiB, MiB, GiB...
+    Iec,
+}
+
+/// How a number of bytes is turned into text
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct SizeFormat {
+    /// Which units, if any, sizes are scaled to
+    pub units: SizeUnits,
+    /// Digits after the decimal point, `None` picks enough for three significant figures
+    pub precision: Option<usize>,
+}
+
+impl Default for SizeFormat {
+    fn default() -> Self {
+        Self { units: SizeUnits::Iec, precision: None }
+    }
+}
+
+impl SizeFormat {
+    /// `bytes` written out, such as `1.50 KiB`
+    pub fn format(&self, bytes: u64) -> String {
+        let (base, suffixes) = match self.units {
+            SizeUnits::Bytes => { return format!("{}", bytes) }
+            SizeUnits::Si => { (1000.0, ["B", "kB", "MB", "GB", "TB", "PB", "EB"]) }
+            SizeUnits::Iec => { (1024.0, ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]) }
+        };
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= base && unit < suffixes.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        if unit == 0 { return format!("{} {}", bytes, suffixes[0]); }
+
+        let decimals = |value: f64| self.precision.unwrap_or(
+            if value < 10.0 { 2 } else if value < 100.0 { 1 } else { 0 });
+        // 1023.97 KiB would otherwise round up to a confusing "1024 KiB"
+        let rounded: f64 = format!("{:.*}", decimals(value), value).parse().unwrap_or(value);
+        if rounded >= base && unit < suffixes.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        format!("{:.*} {}", decimals(value), value, suffixes[unit])
+    }
+}
+
+/// The overall shape of a timestamp
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum DateStyle {
+    /// `2024-06-13 17:48:01`
+    Standard,
+    /// `2024-06-13T17:48:01+01:00`
+    Iso8601,
+    /// `3 hours ago`
+    Relative,
+    /// Whatever strftime pattern is in [`DateFormat::custom_pattern`]
+    Custom,
+}
+
+/// How timestamps are turned into text
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct DateFormat {
+    /// Which shape dates take
+    pub style: DateStyle,
+    /// The strftime pattern used by [`DateStyle::Custom`]
+    pub custom_pattern: String,
+    /// Show times in UTC rather than the local time zone
+    pub utc: bool,
+}
+
+impl Default for DateFormat {
+    fn default() -> Self {
+        Self { style: DateStyle::Standard, custom_pattern: "%F %T".to_string(), utc: false }
+    }
+}
+
+impl DateFormat {
+    /// Formats `time`, or `-` when the file system did not record it
+    pub fn format(&self, time: Option<SystemTime>) -> String {
+        self.format_at(time, SystemTime::now())
+    }
+
+    /// Like [`DateFormat::format`], with relative times counted back from `now`
+    pub fn format_at(&self, time: Option<SystemTime>, now: SystemTime) -> String {
+        let Some(time) = time else { return "-".to_string() };
+        let pattern = match self.style {
+            DateStyle::Standard => { "%F %T" }
+            DateStyle::Iso8601 => { "%Y-%m-%dT%H:%M:%S%:z" }
+            DateStyle::Relative => { return format_relative(time, now) }
+            DateStyle::Custom => {
+                // chrono panics when asked to format with a bad pattern
+                if !self.is_valid() { return "invalid format".to_string() }
+                &self.custom_pattern
+            }
+        };
+        if self.utc { DateTime::<Utc>::from(time).format(pattern).to_string() }
+        else { DateTime::<Local>::from(time).format(pattern).to_string() }
+    }
+
+    /// `false` when a custom pattern has a specifier strftime does not understand
+    pub fn is_valid(&self) -> bool {
+        self.style != DateStyle::Custom
+            || StrftimeItems::new(&self.custom_pattern).all(|item| item != Item::Error)
+    }
+}
+
+fn format_relative(time: SystemTime, now: SystemTime) -> String {
+    let (secs, future) = match now.duration_since(time) {
+        Ok(ago) => (ago.as_secs(), false),
+        Err(e) => (e.duration().as_secs(), true),
+    };
+    const MINUTE: u64 = 60;
+    const HOUR: u64 = 60 * MINUTE;
+    const DAY: u64 = 24 * HOUR;
+    if secs < 45 { return "just now".to_string(); }
+    let (count, unit) = if secs < HOUR { (secs / MINUTE, "minute") }
+        else if secs < DAY { (secs / HOUR, "hour") }
+        else if secs < 30 * DAY { (secs / DAY, "day") }
+        else if secs < 365 * DAY { (secs / (30 * DAY), "month") }
+        else { (secs / (365 * DAY), "year") };
+    let count = count.max(1);
+    let plural = if count == 1 { "" } else { "s" };
+    if future { format!("in {} {}{}", count, unit, plural) } else { format!("{} {}{} ago", count, unit, plural) }
+}
@@ -1,53 +1,264 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
 use std::path::PathBuf;
 use eframe::egui;
+use egui_extras::{Column as TableColumn, TableBuilder};
 
-use egui_extras::{Column, TableBuilder};
-use crate::file_manager::{check_dir_exists, evaluate_path_vars, FileInfo, get_files_in_dir};
-use crate::file_ordering::{DisplayOptions, SortBy};
+use filenewer::{check_dir_exists, evaluate_path_vars, refresh_entries, DirLoader, DirSize, DirSizeJob, DirWatcher,
+                FileInfo, FileNewerError, FileType, LoadEvent};
+use filenewer::{format_widths, parse_widths, CaseOrder, Column, DateStyle, DisplayOptions, SizeUnits, SortBy, SortKey};
+use filenewer::{conflicts, copy_entry, create_dir, create_file, is_valid_name, list_templates, rename_entry, templates_dir,
+                ConflictPolicy, FileJob, FileJobEvent, FileTask};
+use filenewer::{list_trash, restore, trash, FileOp, Journal, Selection, TrashItem};
+use filenewer::{ContentEvent, ContentHit, ContentQuery, ContentSearch};
+use filenewer::{MatchMode, NameFilter, NameMatcher, NameSearch, SearchEvent, SearchHit, TypeFilter};
 
 // CONSTS
 const MIN_CENTRAL_PANEL_WIDTH:f32 = 600.0;
 const DEFAULT_SIDE_BAR_WIDTH:f32 = 150.0;
+// where the display options and column widths are kept between runs
+const DISPLAY_OPTIONS_KEY: &str = "display_options";
+const COLUMN_WIDTHS_KEY: &str = "column_widths";
+const SORT_OPTIONS: [(&str, SortBy); 11] = [
+    ("File Name", SortBy::Name),
+    ("File Type", SortBy::Type),
+    ("File Extension", SortBy::Ext),
+    ("Creation Date", SortBy::CreateDate),
+    ("Modification Date", SortBy::ModDate),
+    ("View Date", SortBy::ViewDate),
+    ("File Size", SortBy::Size),
+    ("Permissions", SortBy::Permissions),
+    ("Owner", SortBy::Owner),
+    ("Group", SortBy::Group),
+    ("Nan", SortBy::Nan),
+];
 
 pub struct FileNewerGui {
     user_facing_path: String,
+    // the folder files_in_cur_path was read from, user_facing_path may be mid edit
+    cur_path: PathBuf,
     error_message: Option<String>,
     files_in_cur_path: Vec<FileInfo>,
-    selected_file: Option<usize>,
+    listing_errors: Vec<FileNewerError>,
+    show_listing_errors: bool,
+    // kept by name, so sorting and entries coming and going leave it alone
+    selection: Selection,
+    // while set, files_in_cur_path is still being filled in from a worker thread
+    loader: Option<DirLoader>,
+    // picked out by name once loading finishes, as indices are not known up front
+    select_on_load: Option<OsString>,
+    // the selection from before the folder was read again, put back on what is still there
+    reselect_on_load: Option<Selection>,
+    load_cancelled: bool,
+    // keeps files_in_cur_path in step with the disk, None if the folder can not be watched
+    watcher: Option<DirWatcher>,
+    // recursive folder sizes worked out so far, kept across folders so going back is instant
+    dir_sizes: HashMap<PathBuf, DirSize>,
+    dir_size_jobs: Vec<DirSizeJob>,
+    // folders a size job is already working out
+    dir_sizes_pending: HashSet<PathBuf>,
     display_options: DisplayOptions,
+    name_filter: NameFilter,
+    // None while the filter is empty or its pattern does not compile
+    filter_matcher: Option<NameMatcher>,
+    filter_error: Option<String>,
+    // indices into files_in_cur_path that get past the filter, in display order
+    visible_rows: Vec<usize>,
+    // entries copied or cut, waiting to be pasted into whichever folder is open then
+    clipboard: Option<Clipboard>,
+    // a file operation waiting on the user to confirm it or fill something in
+    pending_op: Option<PendingOp>,
+    // a copy, move, trash or delete working through its entries in the background
+    file_job: Option<RunningJob>,
+    // the select by pattern window is open while this is set
+    select_pattern: Option<NameFilter>,
+    // an entry whose name is being typed straight into the table
+    inline_rename: Option<InlineRename>,
+    // what file operations have been done, for undo and redo
+    journal: Journal,
+    show_history: bool,
+    name_search: NameSearchPanel,
+    content_search: ContentSearchPanel,
+    trash: TrashPanel,
+    // last width of each table column, so it survives the column being hidden or moved
+    column_widths: HashMap<Column, f32>,
     update_files_this_loop:bool,
 }
 
-impl Default for FileNewerGui {
-    fn default() -> Self {
-        // shouldn't fail
-        let pth = evaluate_path_vars("%USERPROFILE%").unwrap();
-        Self {
-            files_in_cur_path: get_files_in_dir(&pth, &false).expect("REASON"),
-            user_facing_path: pth,
+struct Clipboard {
+    paths: Vec<PathBuf>,
+    // move rather than copy when pasted
+    cut: bool,
+}
+
+enum PendingOp {
+    Delete(Vec<PathBuf>),
+    Rename { path: PathBuf, new_name: String },
+    // some of sources are already in dest_dir, the user picks what to do about them
+    Paste { sources: Vec<PathBuf>, dest_dir: PathBuf, cut: bool, conflicts: Vec<PathBuf>, policy: ConflictPolicy },
+}
+
+struct RunningJob {
+    job: FileJob,
+    // recorded in the journal once the job finishes, None for what can not be undone
+    label: Option<String>,
+    // what the job does, such as `copy`, for the error message
+    action: &'static str,
+    // what the job is doing, such as `Copying`, for the progress shown
+    doing: &'static str,
+    // entries done so far, out of how many, and the one being worked on
+    progress: Option<(usize, usize, PathBuf)>,
+    stopping: bool,
+}
+
+struct InlineRename {
+    // the entry's name on disk, new_name only replaces it once confirmed
+    name: OsString,
+    new_name: String,
+    // the editor is focused, with the name selected, only on the first frame it is shown
+    focused: bool,
+}
+
+// what the New buttons make in the open folder
+enum NewEntry {
+    Folder,
+    File,
+    FromTemplate(PathBuf),
+}
+
+#[derive(Clone, Copy)]
+enum FileAction {
+    Copy,
+    Cut,
+    Paste,
+    Rename,
+    Trash,
+    Delete,
+    Undo,
+    Redo,
+}
+
+// the recursive name search window and whatever it has found so far
+#[derive(Default)]
+struct NameSearchPanel {
+    open: bool,
+    filter: NameFilter,
+    // set while the tree is still being walked
+    search: Option<NameSearch>,
+    // the folder relative_path in each hit is relative to
+    root: PathBuf,
+    hits: Vec<SearchHit>,
+    unreadable: usize,
+    error: Option<String>,
+    selected: Option<usize>,
+}
+
+// the search inside files window and the files found so far
+#[derive(Default)]
+struct ContentSearchPanel {
+    open: bool,
+    query: ContentQuery,
+    search: Option<ContentSearch>,
+    root: PathBuf,
+    hits: Vec<ContentHit>,
+    unreadable: usize,
+    error: Option<String>,
+}
+
+// the trash window, items are only read when it opens or is refreshed
+#[derive(Default)]
+struct TrashPanel {
+    open: bool,
+    items: Vec<TrashItem>,
+    unreadable: usize,
+    selected: Option<usize>,
+}
+
+impl FileNewerGui {
+    // starts in the home folder, with the display options and column widths from last time if there are any
+    pub fn new(cc: &eframe::CreationContext) -> Self {
+        let mut display_options = DisplayOptions::default();
+        let mut column_widths = HashMap::new();
+        if let Some(storage) = cc.storage {
+            if let Some(settings) = storage.get_string(DISPLAY_OPTIONS_KEY) { display_options.apply_settings(&settings); }
+            if let Some(widths) = storage.get_string(COLUMN_WIDTHS_KEY) { column_widths = parse_widths(&widths); }
+        }
+        let mut gui = Self {
+            files_in_cur_path: Vec::new(),
+            listing_errors: Vec::new(),
+            show_listing_errors: false,
+            user_facing_path: "~".to_string(),
+            cur_path: PathBuf::new(),
             error_message: None,
-            selected_file: None,
-            display_options: DisplayOptions::default(),
+            selection: Selection::default(),
+            loader: None,
+            select_on_load: None,
+            reselect_on_load: None,
+            load_cancelled: false,
+            watcher: None,
+            dir_sizes: HashMap::new(),
+            dir_size_jobs: Vec::new(),
+            dir_sizes_pending: HashSet::new(),
+            display_options,
+            name_filter: NameFilter::default(),
+            filter_matcher: None,
+            filter_error: None,
+            visible_rows: Vec::new(),
+            clipboard: None,
+            pending_op: None,
+            file_job: None,
+            select_pattern: None,
+            inline_rename: None,
+            journal: Journal::default(),
+            show_history: false,
+            name_search: NameSearchPanel::default(),
+            content_search: ContentSearchPanel::default(),
+            trash: TrashPanel::default(),
+            column_widths,
             update_files_this_loop:false
-        }
+        };
+        gui.update_working_dir();
+        gui
     }
 }
 
 impl eframe::App for FileNewerGui {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string(DISPLAY_OPTIONS_KEY, self.display_options.settings());
+        storage.set_string(COLUMN_WIDTHS_KEY, format_widths(&self.column_widths));
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         let max_side_panel_width =
             (ctx.available_rect().width() - MIN_CENTRAL_PANEL_WIDTH) / 2.0;
         self.update_files_this_loop = false;
+        self.poll_loader();
+        self.poll_watcher();
+        self.poll_dir_sizes();
+        self.poll_search();
+        self.poll_content_search();
+        self.poll_file_job();
         self.display_menu_bar(ctx);
         self.display_left_side_panel(ctx, &max_side_panel_width);
         self.display_right_side_panel(ctx, &max_side_panel_width);
         self.display_top_panel(ctx);
+        // after everything that may sort or change the listing, so rows line up with files this frame
+        self.apply_filter();
         self.display_main_panel(ctx);
         self.display_error_msg(ctx);
+        self.display_listing_errors(ctx);
+        self.display_search(ctx);
+        self.display_content_search(ctx);
+        self.display_trash(ctx);
+        self.display_history(ctx);
+        self.display_select_pattern(ctx);
+        self.display_pending_op(ctx);
+        self.handle_shortcuts(ctx);
         ctx.request_repaint();
 
         if self.update_files_this_loop {
-            self.selected_file = None;
+            self.selection.clear();
             self.update_working_dir();
         }
 
@@ -72,6 +283,178 @@ impl FileNewerGui {
         }
     }
 
+    fn display_listing_errors(&mut self, ctx: &egui::Context){
+        if !self.show_listing_errors { return; }
+        egui::Window::new("Unreadable Entries")
+            .open(&mut self.show_listing_errors)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for err in &self.listing_errors {
+                        ui.label(err.to_string());
+                    }
+                });
+            });
+    }
+
+    fn display_search(&mut self, ctx: &egui::Context){
+        if !self.name_search.open { return; }
+        let mut open = true;
+        egui::Window::new("Search")
+            .open(&mut open)
+            .default_size([600.0, 400.0])
+            .show(ctx, |ui| {
+                self.build_search(ui);
+            });
+        if !open {
+            self.name_search.open = false;
+            self.name_search.search = None;
+        }
+    }
+
+    fn display_content_search(&mut self, ctx: &egui::Context){
+        if !self.content_search.open { return; }
+        let mut open = true;
+        egui::Window::new("Search Inside Files")
+            .open(&mut open)
+            .default_size([600.0, 400.0])
+            .show(ctx, |ui| {
+                self.build_content_search(ui);
+            });
+        if !open {
+            self.content_search.open = false;
+            self.content_search.search = None;
+        }
+    }
+
+    fn display_trash(&mut self, ctx: &egui::Context){
+        if !self.trash.open { return; }
+        let mut open = true;
+        egui::Window::new("Trash")
+            .open(&mut open)
+            .default_size([600.0, 400.0])
+            .show(ctx, |ui| {
+                self.build_trash(ui);
+            });
+        if !open { self.trash.open = false; }
+    }
+
+    fn display_history(&mut self, ctx: &egui::Context){
+        if !self.show_history { return; }
+        let mut open = true;
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_size([300.0, 400.0])
+            .show(ctx, |ui| {
+                self.build_history(ui);
+            });
+        if !open { self.show_history = false; }
+    }
+
+    fn display_select_pattern(&mut self, ctx: &egui::Context){
+        let Some(filter) = &mut self.select_pattern else { return };
+        let mut open = true;
+        // Some(true) to add to the selection, Some(false) to replace it
+        let mut chosen = None;
+        let mut cancelled = false;
+        let error = filter.compile().err();
+        egui::Window::new("Select by Pattern")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    name_filter_ui(ui, filter, "select_pattern_type_filter");
+                });
+                if let Some(error) = &error { ui.colored_label(ui.visuals().error_fg_color, error.to_string()); }
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(error.is_none(), |ui| {
+                        if ui.button("Select").clicked() { chosen = Some(false); }
+                        if ui.button("Add to Selection").clicked() { chosen = Some(true); }
+                    });
+                    if ui.button("Cancel").clicked() { cancelled = true; }
+                });
+            });
+        if cancelled || !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.select_pattern = None;
+            return;
+        }
+        if let Some(add) = chosen {
+            let Some(filter) = self.select_pattern.take() else { return };
+            if let Err(e) = self.select_matching(&filter, add) { self.error_message = Some(e.to_string()); }
+        }
+    }
+
+    fn display_pending_op(&mut self, ctx: &egui::Context){
+        let Some(op) = &mut self.pending_op else { return };
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        let title = match op {
+            PendingOp::Delete(_) => { "Delete" }
+            PendingOp::Rename { .. } => { "Rename" }
+            PendingOp::Paste { .. } => { "Already Exists" }
+        };
+        egui::Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                match op {
+                    PendingOp::Delete(paths) => {
+                        ui.label(match paths.as_slice() {
+                            [path] => format!("Permanently delete {}?", path.display()),
+                            _ => format!("Permanently delete {} items?", paths.len()),
+                        });
+                        ui.label("This can not be undone.");
+                        ui.horizontal(|ui| {
+                            confirmed = ui.button("Delete").clicked();
+                            if ui.button("Cancel").clicked() { cancelled = true; }
+                        });
+                    }
+                    PendingOp::Rename { path, new_name } => {
+                        ui.label(format!("New name for {}", path.file_name().unwrap_or_default().to_string_lossy()));
+                        let text = ui.text_edit_singleline(new_name);
+                        text.request_focus();
+                        let valid = is_valid_name(new_name);
+                        if !valid { ui.colored_label(ui.visuals().error_fg_color, "Not a valid name"); }
+                        ui.horizontal(|ui| {
+                            let enter = text.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                            if ui.add_enabled(valid, egui::Button::new("Rename")).clicked() || (valid && enter) {
+                                confirmed = true;
+                            }
+                            if ui.button("Cancel").clicked() { cancelled = true; }
+                        });
+                    }
+                    PendingOp::Paste { conflicts, dest_dir, policy, .. } => {
+                        ui.label(match conflicts.as_slice() {
+                            [path] => format!("{} already has something called {}",
+                                              dest_dir.display(), path.file_name().unwrap_or_default().to_string_lossy()),
+                            _ => format!("{} items are already in {}", conflicts.len(), dest_dir.display()),
+                        });
+                        ui.horizontal(|ui| {
+                            let choices = [(ConflictPolicy::Overwrite, "Overwrite"), (ConflictPolicy::Skip, "Skip"),
+                                (ConflictPolicy::KeepBoth, "Keep Both")];
+                            for (choice, label) in choices {
+                                if ui.button(label).clicked() {
+                                    *policy = choice;
+                                    confirmed = true;
+                                }
+                            }
+                            if ui.button("Cancel").clicked() { cancelled = true; }
+                        });
+                    }
+                }
+            });
+        if cancelled || !open || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.pending_op = None;
+            return;
+        }
+        if confirmed {
+            if let Some(op) = self.pending_op.take() { self.run_pending_op(op); }
+        }
+    }
+
     fn display_left_side_panel(&mut self, ctx: &egui::Context, max_width:&f32){
         egui::SidePanel::left("File_Tree")
             .resizable(true)
@@ -115,11 +498,516 @@ impl FileNewerGui {
     }
 }
 
+// STATE
+impl FileNewerGui {
+    // the selected entry when exactly one is, None while the listing is about to be replaced
+    fn selected(&self) -> Option<&FileInfo> {
+        if self.update_files_this_loop || self.selection.len() != 1 { return None; }
+        self.files_in_cur_path.iter().find(|file| self.selection.contains(&file.file_name))
+    }
+
+    // every selected entry, in the order they are shown
+    fn selected_files(&self) -> impl Iterator<Item = &FileInfo> {
+        let files = if self.update_files_this_loop { &[][..] } else { &self.files_in_cur_path[..] };
+        self.visible_rows.iter()
+            .filter_map(move |idx| files.get(*idx))
+            .filter(|file| self.selection.contains(&file.file_name))
+    }
+
+    fn select_all(&mut self) {
+        self.selection.select_all(shown_names(&self.files_in_cur_path, &self.visible_rows));
+    }
+
+    fn invert_selection(&mut self) {
+        self.selection.invert(shown_names(&self.files_in_cur_path, &self.visible_rows));
+    }
+
+    // selects the shown entries filter matches, on top of what is already selected when adding
+    fn select_matching(&mut self, filter: &NameFilter, add: bool) -> Result<(), FileNewerError> {
+        let matcher = filter.compile()?;
+        if !add { self.selection.clear(); }
+        let files = &self.files_in_cur_path;
+        self.selection.select_all(self.visible_rows.iter()
+            .map(|idx| &files[*idx])
+            .filter(|file| matcher.matches(file))
+            .map(|file| file.file_name.as_os_str()));
+        Ok(())
+    }
+
+    fn compile_filter(&mut self) {
+        match self.name_filter.compile() {
+            Ok(matcher) => {
+                self.filter_matcher = Some(matcher);
+                self.filter_error = None;
+            }
+            Err(e) => {
+                self.filter_matcher = None;
+                self.filter_error = Some(e.to_string());
+            }
+        }
+    }
+
+    fn apply_filter(&mut self) {
+        let matcher = self.filter_matcher.as_ref().filter(|_| self.name_filter.is_active());
+        let files = &self.files_in_cur_path;
+        self.visible_rows.clear();
+        self.visible_rows.extend((0..files.len())
+            .filter(|idx| matcher.is_none_or(|matcher| matcher.matches(&files[*idx]))));
+        // nothing hidden by the filter, or gone from the disk, stays selected
+        if !self.selection.is_empty() && self.loader.is_none() {
+            let shown: HashSet<&OsStr> = self.visible_rows.iter().map(|idx| files[*idx].file_name.as_os_str()).collect();
+            self.selection.retain(|name| shown.contains(name));
+        }
+    }
+
+    fn select_by_name(&mut self, name: &OsStr) {
+        self.selection.select_only(name);
+    }
+
+    fn sort_files(&mut self) {
+        self.display_options.sort(&mut self.files_in_cur_path);
+    }
+
+    fn poll_loader(&mut self) {
+        let Some(loader) = &mut self.loader else { return };
+        for event in loader.poll() {
+            match event {
+                LoadEvent::Batch(listing) => {
+                    self.files_in_cur_path.extend(listing.files);
+                    self.listing_errors.extend(listing.errors);
+                }
+                LoadEvent::Done => {
+                    self.sort_files();
+                    if let Some(mut selection) = self.reselect_on_load.take() {
+                        let files = &self.files_in_cur_path;
+                        selection.retain(|name| files.iter().any(|file| file.file_name == name));
+                        self.selection = selection;
+                    }
+                    if let Some(name) = self.select_on_load.take() { self.select_by_name(&name); }
+                    self.update_dir_sizes();
+                }
+                LoadEvent::Failed(e) => {
+                    self.error_message = Some(format!("Cannot read contents of folder as {}", e));
+                }
+            }
+        }
+        if self.loader.as_ref().is_some_and(DirLoader::is_finished) { self.loader = None; }
+    }
+
+    // applies whatever changed on disk, held back until loading is done so nothing is added twice
+    fn poll_watcher(&mut self) {
+        if self.loader.is_some() { return; }
+        let Some(watcher) = &self.watcher else { return };
+        let changes = watcher.poll();
+        if changes.is_empty() { return; }
+        if changes.rescan {
+            // the same folder again, so the path bar is left as it is in case it is being typed in
+            let (selection, created) = (self.selection.clone(), self.select_on_load.take());
+            self.load_dir(self.cur_path.clone());
+            self.reselect_on_load = Some(selection);
+            self.select_on_load = created;
+            return;
+        }
+        let errors = refresh_entries(&mut self.files_in_cur_path, &changes.paths,
+                                     self.display_options.show_hidden);
+        self.listing_errors.extend(errors);
+        // a change anywhere below a folder changes the size of it and of everything above it
+        for path in &changes.paths {
+            for dir in path.ancestors() {
+                self.dir_sizes.remove(dir);
+                self.dir_sizes_pending.remove(dir);
+            }
+        }
+        self.update_dir_sizes();
+        self.display_options.sort(&mut self.files_in_cur_path);
+        // something just made here is picked out as soon as it shows up
+        let files = &self.files_in_cur_path;
+        let created = self.select_on_load.take_if(|name| files.iter().any(|file| file.file_name == *name));
+        if let Some(name) = created { self.select_by_name(&name); }
+    }
+
+    // fills in folder sizes already known and starts working out the rest
+    fn update_dir_sizes(&mut self) {
+        if !self.display_options.calc_dir_sizes {
+            self.dir_size_jobs.clear();
+            self.dir_sizes_pending.clear();
+            return;
+        }
+        let mut missing = Vec::new();
+        for file in self.files_in_cur_path.iter_mut().filter(|file| file.is_dir()) {
+            let path = self.cur_path.join(&file.file_name);
+            match self.dir_sizes.get(&path) {
+                Some(size) => file.dir_size = Some(*size),
+                None if !self.dir_sizes_pending.contains(&path) => missing.push(path),
+                None => {}
+            }
+        }
+        // jobs already running are left alone so a busy folder does not keep starting over
+        if missing.is_empty() { return; }
+        self.dir_sizes_pending.extend(missing.iter().cloned());
+        self.dir_size_jobs.push(DirSizeJob::spawn(missing));
+    }
+
+    fn poll_dir_sizes(&mut self) {
+        let results: Vec<_> = self.dir_size_jobs.iter_mut().flat_map(DirSizeJob::poll).collect();
+        self.dir_size_jobs.retain(|job| !job.is_finished());
+        if self.dir_size_jobs.is_empty() { self.dir_sizes_pending.clear(); }
+        if results.is_empty() { return; }
+        for (path, size) in results {
+            self.dir_sizes_pending.remove(&path);
+            if path.parent() == Some(self.cur_path.as_path()) {
+                if let Some(file) = self.files_in_cur_path.iter_mut()
+                    .find(|file| Some(file.file_name.as_os_str()) == path.file_name()) {
+                    file.dir_size = Some(size);
+                }
+            }
+            self.dir_sizes.insert(path, size);
+        }
+        if self.display_options.sorts_by(&SortBy::Size) { self.sort_files(); }
+    }
+
+    fn start_search(&mut self) {
+        let panel = &mut self.name_search;
+        panel.hits.clear();
+        panel.unreadable = 0;
+        panel.selected = None;
+        let matcher = match panel.filter.compile() {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                panel.error = Some(e.to_string());
+                return;
+            }
+        };
+        match evaluate_path_vars(&self.user_facing_path) {
+            Ok(root) => {
+                panel.error = None;
+                panel.root = root.clone();
+                // replacing the search drops, and so cancels, the one before
+                panel.search = Some(NameSearch::spawn(root, matcher, self.display_options.show_hidden));
+            }
+            Err(e) => { panel.error = Some(format!("Cannot search, as {}", e)); }
+        }
+    }
+
+    fn poll_search(&mut self) {
+        let panel = &mut self.name_search;
+        let Some(search) = &mut panel.search else { return };
+        for event in search.poll() {
+            match event {
+                SearchEvent::Batch { hits, errors } => {
+                    panel.hits.extend(hits);
+                    panel.unreadable += errors.len();
+                }
+                SearchEvent::Done => {}
+                SearchEvent::Failed(e) => { panel.error = Some(format!("Cannot search, as {}", e)); }
+            }
+        }
+        if search.is_finished() { panel.search = None; }
+    }
+
+    fn start_content_search(&mut self) {
+        let panel = &mut self.content_search;
+        panel.hits.clear();
+        panel.unreadable = 0;
+        if panel.query.pattern.is_empty() { return; }
+        let regex = match panel.query.compile() {
+            Ok(regex) => regex,
+            Err(e) => {
+                panel.error = Some(e.to_string());
+                return;
+            }
+        };
+        match evaluate_path_vars(&self.user_facing_path) {
+            Ok(root) => {
+                panel.error = None;
+                panel.root = root.clone();
+                panel.search = Some(ContentSearch::spawn(root, regex, panel.query.context,
+                                                         self.display_options.show_hidden));
+            }
+            Err(e) => { panel.error = Some(format!("Cannot search, as {}", e)); }
+        }
+    }
+
+    fn poll_content_search(&mut self) {
+        let panel = &mut self.content_search;
+        let Some(search) = &mut panel.search else { return };
+        for event in search.poll() {
+            match event {
+                ContentEvent::Hit(hit) => { panel.hits.push(hit); }
+                ContentEvent::Unreadable(_) => { panel.unreadable += 1; }
+                ContentEvent::Done => {}
+                ContentEvent::Failed(e) => { panel.error = Some(format!("Cannot search, as {}", e)); }
+            }
+        }
+        if search.is_finished() { panel.search = None; }
+    }
+
+    // full paths of what the file actions work on
+    fn selected_paths(&self) -> Vec<PathBuf> {
+        self.selected_files().map(|file| self.cur_path.join(&file.file_name)).collect()
+    }
+
+    fn can_run(&self, action: FileAction) -> bool {
+        // one job at a time, so the journal records them in the order they happen
+        if self.file_job.is_some() && !matches!(action, FileAction::Copy | FileAction::Cut) { return false; }
+        match action {
+            FileAction::Paste => { self.clipboard.is_some() }
+            FileAction::Rename => { self.selected().is_some() }
+            FileAction::Trash => { trash::SUPPORTED && !self.selected_paths().is_empty() }
+            FileAction::Undo => { self.journal.next_undo().is_some() }
+            FileAction::Redo => { self.journal.next_redo().is_some() }
+            FileAction::Copy | FileAction::Cut | FileAction::Delete => { !self.selected_paths().is_empty() }
+        }
+    }
+
+    fn run_action(&mut self, action: FileAction) {
+        if !self.can_run(action) { return; }
+        match action {
+            FileAction::Copy | FileAction::Cut => {
+                self.clipboard = Some(Clipboard { paths: self.selected_paths(), cut: matches!(action, FileAction::Cut) });
+            }
+            FileAction::Paste => {
+                let Some(clipboard) = &self.clipboard else { return };
+                let (sources, cut, dest_dir) = (clipboard.paths.clone(), clipboard.cut, self.cur_path.clone());
+                let conflicts = conflicts(&sources, &dest_dir);
+                // only ask when there is something to decide
+                let ask = !conflicts.is_empty();
+                let op = PendingOp::Paste { sources, dest_dir, cut, conflicts, policy: ConflictPolicy::Skip };
+                if ask { self.pending_op = Some(op); } else { self.run_pending_op(op); }
+            }
+            FileAction::Rename => {
+                let Some(path) = self.selected_paths().pop() else { return };
+                let new_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                self.pending_op = Some(PendingOp::Rename { path, new_name });
+            }
+            FileAction::Trash => {
+                // the trash is the undo for this, so there is nothing to confirm
+                self.start_file_job(FileTask::Trash(self.selected_paths()));
+            }
+            FileAction::Delete => { self.pending_op = Some(PendingOp::Delete(self.selected_paths())); }
+            FileAction::Undo | FileAction::Redo => {
+                let undo = matches!(action, FileAction::Undo);
+                let entry = if undo { self.journal.next_undo() } else { self.journal.next_redo() };
+                let label = entry.map(|entry| entry.label.clone()).unwrap_or_default();
+                let replay = if undo { self.journal.undo() } else { self.journal.redo() };
+                if !replay.errors.is_empty() {
+                    let verb = if undo { "undo" } else { "redo" };
+                    let lines: Vec<String> = replay.errors.iter().map(|e| e.to_string()).collect();
+                    self.error_message = Some(if replay.dropped {
+                        format!("Could not {} all of \"{}\":\n{}\nThe rest was done, the part that failed has been dropped from the history.",
+                                verb, label, lines.join("\n"))
+                    } else {
+                        format!("Could not {} \"{}\":\n{}\nNothing was changed, it can be tried again.", verb, label, lines.join("\n"))
+                    });
+                }
+                self.files_changed();
+            }
+        }
+    }
+
+    fn run_pending_op(&mut self, op: PendingOp) {
+        match op {
+            PendingOp::Delete(paths) => { self.start_file_job(FileTask::Delete(paths)); }
+            PendingOp::Rename { path, new_name } => {
+                match rename_entry(&path, &new_name) {
+                    Ok(to) if to != path => {
+                        self.journal.record(op_label("Rename", std::slice::from_ref(&path)), vec![FileOp::Move { from: path, to }]);
+                    }
+                    Ok(_) => {}
+                    Err(e) => { self.report_errors("rename", &[e]); }
+                }
+                self.files_changed();
+            }
+            PendingOp::Paste { sources, dest_dir, cut, policy, .. } => {
+                // what was cut is gone from where it was, so it can not be pasted again
+                if cut { self.clipboard = None; }
+                self.start_file_job(FileTask::Paste { sources, dest_dir, cut, policy });
+            }
+        }
+    }
+
+    fn start_file_job(&mut self, task: FileTask) {
+        let (verb, action, doing) = match &task {
+            FileTask::Paste { cut: true, .. } => { ("Move", "move", "Moving") }
+            FileTask::Paste { .. } => { ("Copy", "copy", "Copying") }
+            FileTask::Trash(_) => { ("Trash", "move to the trash", "Moving to the trash") }
+            FileTask::Delete(_) => { ("Delete", "delete", "Deleting") }
+        };
+        let label = match &task {
+            // there is nothing left to undo this with, so it is not recorded
+            FileTask::Delete(_) => { None }
+            // overwritten entries are gone for good, so undoing would only bring half back
+            FileTask::Paste { policy: ConflictPolicy::Overwrite, .. } if !trash::SUPPORTED => {
+                self.error_message = Some("The overwritten entries are deleted for good, so this paste can not be undone."
+                    .to_string());
+                None
+            }
+            _ => { Some(op_label(verb, task.paths())) }
+        };
+        let job = FileJob::spawn(task);
+        self.file_job = Some(RunningJob { job, label, action, doing, progress: None, stopping: false });
+    }
+
+    fn poll_file_job(&mut self) {
+        let Some(running) = &mut self.file_job else { return };
+        let mut finished = None;
+        for event in running.job.poll() {
+            match event {
+                FileJobEvent::Progress { done, total, current } => { running.progress = Some((done, total, current)); }
+                FileJobEvent::Finished { ops, errors } => { finished = Some((ops, errors)); }
+            }
+        }
+        if !running.job.is_finished() { return; }
+        let Some(running) = self.file_job.take() else { return };
+        if let Some((ops, errors)) = finished {
+            if let Some(label) = running.label { self.journal.record(label, ops); }
+            self.report_errors(running.action, &errors);
+        }
+        self.files_changed();
+    }
+
+    // after a file operation, the watcher picks up the changes, without one the folder has to be read again
+    fn files_changed(&mut self) {
+        if self.watcher.is_none() { self.update_files_this_loop = true; }
+        if self.trash.open { self.refresh_trash(); }
+    }
+
+    // makes the entry with a placeholder name, then lets the user type the real one into the table
+    fn create_entry(&mut self, new: NewEntry) {
+        let dir = self.cur_path.clone();
+        let created = match new {
+            NewEntry::Folder => { create_dir(&dir, "New Folder").map(|path| (FileOp::CreateDir(path.clone()), path)) }
+            NewEntry::File => { create_file(&dir, "New File").map(|path| (FileOp::CreateFile(path.clone()), path)) }
+            NewEntry::FromTemplate(template) => {
+                // keeping both never skips, so there is always a path
+                copy_entry(&template, &dir, ConflictPolicy::KeepBoth)
+                    .map(|path| path.unwrap_or_default())
+                    .and_then(|path| Ok((FileOp::copied(template, path.clone())?, path)))
+            }
+        };
+        match created {
+            Ok((op, path)) => {
+                self.journal.record(op_label("New", std::slice::from_ref(&path)), vec![op]);
+                let name = path.file_name().unwrap_or_default().to_os_string();
+                self.inline_rename = Some(InlineRename {
+                    new_name: name.to_string_lossy().into_owned(), name: name.clone(), focused: false,
+                });
+                self.select_when_listed(name);
+            }
+            Err(e) => { self.error_message = Some(format!("Could not create: {}", e)); }
+        }
+    }
+
+    fn finish_inline_rename(&mut self, confirmed: bool) {
+        let Some(rename) = self.inline_rename.take() else { return };
+        if !confirmed || rename.name == OsStr::new(&rename.new_name) { return; }
+        let path = self.cur_path.join(&rename.name);
+        match rename_entry(&path, &rename.new_name) {
+            Ok(to) => {
+                self.journal.record(op_label("Rename", std::slice::from_ref(&path)), vec![FileOp::Move { from: path, to }]);
+                self.select_when_listed(OsString::from(rename.new_name));
+            }
+            Err(e) => { self.error_message = Some(format!("Could not rename: {}", e)); }
+        }
+    }
+
+    // picks out name once it is in the listing, reading the folder again when nothing is watching it
+    fn select_when_listed(&mut self, name: OsString) {
+        if self.watcher.is_none() { self.update_working_dir(); }
+        self.select_on_load = Some(name);
+    }
+
+    fn report_errors(&mut self, action: &str, errors: &[FileNewerError]) {
+        if errors.is_empty() { return; }
+        let lines: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        self.error_message = Some(format!("Could not {} everything:\n{}", action, lines.join("\n")));
+    }
+
+    fn refresh_trash(&mut self) {
+        let (mut items, errors) = list_trash();
+        // most recently trashed first, as that is what is most likely wanted back
+        items.sort_by_key(|item| Reverse(item.deleted));
+        self.trash.items = items;
+        self.trash.unreadable = errors.len();
+        self.trash.selected = None;
+    }
+
+    fn restore_selected_trash(&mut self) {
+        let Some(item) = self.trash.selected.and_then(|idx| self.trash.items.get(idx)) else { return };
+        match restore(item) {
+            Ok(path) => {
+                if self.watcher.is_none() && path.parent() == Some(self.cur_path.as_path()) {
+                    self.update_files_this_loop = true;
+                }
+            }
+            Err(e) => { self.error_message = Some(format!("Could not restore: {}", e)); }
+        }
+        self.refresh_trash();
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // keys typed into a text box are not meant for the file list
+        if ctx.wants_keyboard_input() || self.pending_op.is_some() { return; }
+        let (new_folder, select_all, invert) = ctx.input_mut(|i| (
+            i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::N),
+            i.consume_key(egui::Modifiers::COMMAND, egui::Key::A),
+            i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::I),
+        ));
+        if new_folder { self.create_entry(NewEntry::Folder); }
+        if select_all { self.select_all(); }
+        if invert { self.invert_selection(); }
+        let action = ctx.input_mut(|i| {
+            // shift is checked first as the plain shortcuts would match with it held too
+            if i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z) { Some(FileAction::Redo) }
+            else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z) { Some(FileAction::Undo) }
+            else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y) { Some(FileAction::Redo) }
+            else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::C) { Some(FileAction::Copy) }
+            else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::X) { Some(FileAction::Cut) }
+            else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::V) { Some(FileAction::Paste) }
+            else if i.consume_key(egui::Modifiers::NONE, egui::Key::F2) { Some(FileAction::Rename) }
+            else if i.consume_key(egui::Modifiers::SHIFT, egui::Key::Delete) { Some(FileAction::Delete) }
+            else if i.consume_key(egui::Modifiers::NONE, egui::Key::Delete) { Some(FileAction::Trash) }
+            else { None }
+        });
+        if let Some(action) = action { self.run_action(action); }
+    }
+
+    fn cancel_loading(&mut self) {
+        if let Some(mut loader) = self.loader.take() {
+            loader.cancel();
+            self.load_cancelled = true;
+            self.select_on_load = None;
+            self.sort_files();
+            self.update_dir_sizes();
+        }
+    }
+}
+
 // BUILD ITEMS
 impl FileNewerGui {
     fn build_menu(&mut self, ui: &mut egui::Ui){
         egui::menu::bar(ui, |ui|{
             ui.menu_button("File", |ui| {
+                let undo = self.journal.next_undo().map_or("Undo".to_string(), |entry| format!("Undo {}", entry.label));
+                let redo = self.journal.next_redo().map_or("Redo".to_string(), |entry| format!("Redo {}", entry.label));
+                let mut clicked = self.action_button(ui, FileAction::Undo, &undo, "Ctrl+Z");
+                clicked = clicked.or(self.action_button(ui, FileAction::Redo, &redo, "Ctrl+Y"));
+                ui.separator();
+                if let Some(new) = self.new_entry_buttons(ui) { self.create_entry(new); }
+                ui.separator();
+                clicked = clicked.or(self.file_action_buttons(ui));
+                if let Some(action) = clicked { self.run_action(action); }
+                ui.separator();
+                if ui.add_enabled(trash::SUPPORTED, egui::Button::new("Show Trash")).clicked() {
+                    self.trash.open = true;
+                    self.refresh_trash();
+                    ui.close_menu();
+                }
+                if ui.button("Show History").clicked() {
+                    self.show_history = true;
+                    ui.close_menu();
+                }
+                ui.separator();
                 if ui.button("Save").clicked() {
                     self.error_message = Some("Saving".to_string());
                 }
@@ -127,6 +1015,25 @@ impl FileNewerGui {
                     std::process::exit(0);
                 }
             });
+            ui.menu_button("Select", |ui| {
+                if ui.add(egui::Button::new("Select All").shortcut_text("Ctrl+A")).clicked() {
+                    self.select_all();
+                    ui.close_menu();
+                }
+                if ui.button("Select None").clicked() {
+                    self.selection.clear();
+                    ui.close_menu();
+                }
+                if ui.add(egui::Button::new("Invert Selection").shortcut_text("Ctrl+Shift+I")).clicked() {
+                    self.invert_selection();
+                    ui.close_menu();
+                }
+                ui.separator();
+                if ui.button("Select by Pattern...").clicked() {
+                    self.select_pattern = Some(NameFilter { mode: MatchMode::Glob, ..NameFilter::default() });
+                    ui.close_menu();
+                }
+            });
             ui.menu_button("Settings", |ui|{
                 ui.label("SHOW");
                 if ui.checkbox(&mut self.display_options.show_hidden, "Hidden Files").changed(){
@@ -138,30 +1045,124 @@ impl FileNewerGui {
                 ui.checkbox(&mut self.display_options.show_last_mod, "Last Modification Time");
                 ui.checkbox(&mut self.display_options.show_last_acc, "Last Access Time");
                 ui.checkbox(&mut self.display_options.show_file_type, "Show Type Letter");
+                ui.checkbox(&mut self.display_options.show_permissions, "Permissions");
+                ui.checkbox(&mut self.display_options.show_owner, "Owner");
+                ui.checkbox(&mut self.display_options.show_group, "Group");
+                ui.separator();
+                if ui.checkbox(&mut self.display_options.calc_dir_sizes, "Calculate Folder Sizes").changed(){
+                    self.update_dir_sizes();
+                };
+                ui.separator();
+                ui.label("SIZE UNITS");
+                let size_format = &mut self.display_options.size_format;
+                ui.radio_value(&mut size_format.units, SizeUnits::Bytes, "Bytes");
+                ui.radio_value(&mut size_format.units, SizeUnits::Si, "SI (kB, MB)");
+                ui.radio_value(&mut size_format.units, SizeUnits::Iec, "IEC (KiB, MiB)");
+                ui.add_enabled_ui(size_format.units != SizeUnits::Bytes, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Decimals");
+                        ui.selectable_value(&mut size_format.precision, None, "Auto");
+                        for decimals in 0..=3 {
+                            ui.selectable_value(&mut size_format.precision, Some(decimals), decimals.to_string());
+                        }
+                    });
+                });
+                ui.separator();
+                ui.label("DATES");
+                let date_format = &mut self.display_options.date_format;
+                ui.radio_value(&mut date_format.style, DateStyle::Standard, "Standard (2024-06-13 17:48:01)");
+                ui.radio_value(&mut date_format.style, DateStyle::Iso8601, "ISO 8601 with offset");
+                ui.radio_value(&mut date_format.style, DateStyle::Relative, "Relative (3 hours ago)");
+                ui.radio_value(&mut date_format.style, DateStyle::Custom, "Custom strftime pattern");
+                if date_format.style == DateStyle::Custom {
+                    ui.text_edit_singleline(&mut date_format.custom_pattern);
+                    if !date_format.is_valid() {
+                        ui.colored_label(ui.visuals().error_fg_color, "Invalid pattern");
+                    }
+                }
+                ui.add_enabled(date_format.style != DateStyle::Relative,
+                               egui::Checkbox::new(&mut date_format.utc, "Show in UTC"));
                 ui.separator();
             });
             ui.menu_button("Sort by", |ui| {
-                if ui.checkbox(&mut self.display_options.filter_dec, "Sort Descending").changed(){
-                    self.update_files_this_loop = true;
-                };
-                let sort_options = [
-                    ("File Name", SortBy::Name),
-                    ("File Type", SortBy::Type),
-                    ("File Extension", SortBy::Ext),
-                    ("Creation Date", SortBy::CreateDate),
-                    ("Modification Date", SortBy::ModDate),
-                    ("View Date", SortBy::ViewDate),
-                    ("File Size", SortBy::Size),
-                    ("Nan", SortBy::Nan),
-                ];
-                for (label, sort_by) in sort_options.iter() {
+                let mut changed = ui.checkbox(&mut self.display_options.filter_dec, "Sort Descending").changed();
+                changed |= ui.checkbox(&mut self.display_options.dirs_first, "Folders First").changed();
+                ui.separator();
+                for (label, sort_by) in SORT_OPTIONS.iter() {
                     if ui.button(format!("{}{}", if self.display_options.sort_by == *sort_by {"*"} else {""}, label)).clicked() {
                         self.display_options.sort_by = sort_by.clone();
-                        self.update_files_this_loop = true;
+                        changed = true;
                     }
                 }
+                ui.separator();
+                ui.menu_button("Names", |ui| {
+                    let name_order = &mut self.display_options.name_order;
+                    changed |= ui.checkbox(&mut name_order.natural, "Numbers by Value").changed();
+                    changed |= ui.checkbox(&mut name_order.ignore_accents, "Ignore Accents").changed();
+                    ui.separator();
+                    changed |= ui.radio_value(&mut name_order.case, CaseOrder::Sensitive, "Case Sensitive").changed();
+                    changed |= ui.radio_value(&mut name_order.case, CaseOrder::AsciiInsensitive, "Ignore Case (A-Z only)").changed();
+                    changed |= ui.radio_value(&mut name_order.case, CaseOrder::Folded, "Ignore Case").changed();
+                });
+                ui.menu_button("Then by", |ui| {
+                    let then_by = &mut self.display_options.then_by;
+                    for (label, sort_by) in SORT_OPTIONS.iter().filter(|(_, sort_by)| *sort_by != SortBy::Nan) {
+                        let position = then_by.iter().position(|key| key.by == *sort_by);
+                        let text = match position {
+                            Some(idx) => format!("{}. {}", idx + 1, label),
+                            None => label.to_string(),
+                        };
+                        if ui.button(text).clicked() {
+                            match position {
+                                Some(idx) => { then_by.remove(idx); }
+                                None => then_by.push(SortKey { by: sort_by.clone(), descending: false }),
+                            }
+                            changed = true;
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Clear").clicked() {
+                        then_by.clear();
+                        changed = true;
+                    }
+                });
+                if changed { self.sort_files(); }
             });
-            ui.label(format!("Files in Current DIR {}", self.files_in_cur_path.len()))
+            if self.loader.is_some() {
+                ui.spinner();
+                ui.label(format!("Loading {} entries", self.files_in_cur_path.len()));
+                if ui.button("Cancel").clicked() { self.cancel_loading(); }
+            } else {
+                let shown = if self.name_filter.is_active() {
+                    format!("{} of {}", self.visible_rows.len(), self.files_in_cur_path.len())
+                } else { self.files_in_cur_path.len().to_string() };
+                ui.label(format!("Files in Current DIR {}{}", shown,
+                                 if self.load_cancelled {" (cancelled)"} else {""}));
+            }
+            if !self.dir_size_jobs.is_empty() {
+                ui.spinner();
+                ui.label("Sizing folders");
+            }
+            if let Some(running) = &mut self.file_job {
+                ui.spinner();
+                match &running.progress {
+                    Some((done, total, current)) => {
+                        ui.add(egui::ProgressBar::new(*done as f32 / *total as f32).desired_width(100.0));
+                        ui.label(format!("{} {} of {}, {}", running.doing, done + 1, total,
+                                         current.file_name().unwrap_or_default().to_string_lossy()));
+                    }
+                    None => { ui.label(running.doing); }
+                }
+                if running.stopping { ui.label("Stopping after this one"); }
+                else if ui.button("Cancel").clicked() {
+                    running.job.stop();
+                    running.stopping = true;
+                }
+            }
+            if !self.listing_errors.is_empty()
+                && ui.button(format!("{} Unreadable", self.listing_errors.len())).clicked() {
+                self.show_listing_errors = true;
+            }
         });
     }
 
@@ -173,37 +1174,93 @@ impl FileNewerGui {
     }
 
     fn build_side_panel_right(&mut self, ui: &mut egui::Ui) {
+        if self.selection.len() > 1 {
+            self.build_selection_summary(ui);
+            return;
+        }
+        let mut go_to = None;
 
         ui.vertical_centered(|ui| {
-            ui.heading(
-                if !(self.selected_file == None || self.update_files_this_loop){
-                    &self.files_in_cur_path[self.selected_file.unwrap()].file_name.to_str().unwrap()}
-                else {""});
+            ui.heading(match self.selected() {
+                Some(active) => active.file_name.to_string_lossy(),
+                None => "".into()
+            });
         });
         egui::ScrollArea::vertical().show(ui, |ui| {
-            if self.selected_file == None || self.update_files_this_loop{
-                ui.label("Type: -");
-                ui.label("Has Write Perms: -");
-                ui.label("File Name: -");
-                ui.label("File Extension: -");
-
-                ui.label("Last Read : -");
-                ui.label("Last Write: -");
-                ui.label("Created   : -");
-            }
-            else {
-                let active = &self.files_in_cur_path[self.selected_file.unwrap()];
-                ui.label(format!("Type: {}", active.type_to_basic_str()));
-                ui.label(format!("Has Write Perms: {}", active.can_be_written));
-                ui.label(format!("File Name: {}", active.file_name.to_str().unwrap()));
-                ui.label(format!("File Extension: {}",
-                    match &active.file_ext{ Some(ext) => {ext} None => {"-"}}));
-
-                ui.label(format!("Last Write: {}", active.last_mod_formated()));
-                ui.label(format!("Last Read : {}", active.last_access_formated()));
-                ui.label(format!("Created   : {}", active.creation_time_formated()));
+            match self.selected() {
+                None => {
+                    ui.label("Type: -");
+                    ui.label("Has Write Perms: -");
+                    ui.label("Permissions: -");
+                    ui.label("Owner: -");
+                    ui.label("Group: -");
+                    ui.label("File Name: -");
+                    ui.label("File Extension: -");
+                    ui.label("Size: -");
+
+                    ui.label("Last Read : -");
+                    ui.label("Last Write: -");
+                    ui.label("Created   : -");
+                }
+                Some(active) => {
+                    ui.label(format!("Type: {}", active.type_to_basic_str()));
+                    ui.label(format!("Has Write Perms: {}", active.can_be_written));
+                    ui.label(format!("Permissions: {}", active.permissions_formated()));
+                    ui.label(format!("Owner: {}", active.owner.as_deref().unwrap_or("-")));
+                    ui.label(format!("Group: {}", active.group.as_deref().unwrap_or("-")));
+                    ui.label(match active.name_str() {
+                        Ok(name) => format!("File Name: {}", name),
+                        Err(e) => format!("File Name: {} ({})", active.file_name.to_string_lossy(), e),
+                    });
+                    ui.label(format!("File Extension: {}",
+                        match &active.file_ext{ Some(ext) => {ext} None => {"-"}}));
+                    ui.label(format!("Size: {}", active.size_formated(&self.display_options.size_format)));
+                    if let Some(size) = active.dir_size {
+                        ui.label(format!("Files Inside: {}", size.files));
+                    }
+
+                    let date_format = &self.display_options.date_format;
+                    ui.label(format!("Last Write: {}", active.last_mod_formated(date_format)));
+                    ui.label(format!("Last Read : {}", active.last_access_formated(date_format)));
+                    ui.label(format!("Created   : {}", active.creation_time_formated(date_format)));
+
+                    if let Some(target) = &active.link_target {
+                        ui.separator();
+                        ui.label(format!("Link Target: {}", target.display()));
+                        let broken = active.file_type == FileType::BrokenLink;
+                        if ui.add_enabled(!broken, egui::Button::new("Go to target")).clicked() {
+                            go_to = active.resolve_link_target(&self.cur_path);
+                        }
+                    }
+                }
+            }
+        });
+        if let Some(target) = go_to { self.go_to_path(target); }
+    }
+
+    // what the right panel shows in place of one entry's details when several are selected
+    fn build_selection_summary(&self, ui: &mut egui::Ui) {
+        let (mut files, mut dirs, mut bytes, mut unknown) = (0, 0, 0, 0);
+        for file in self.selected_files() {
+            if file.is_dir() { dirs += 1; } else { files += 1; }
+            match file.total_size() {
+                Some(size) => { bytes += size }
+                None => { unknown += 1 }
             }
+        }
+        ui.vertical_centered(|ui| {
+            ui.heading(format!("{} Selected", files + dirs));
         });
+        ui.label(format!("Files: {}", files));
+        ui.label(format!("Folders: {}", dirs));
+        let size = self.display_options.size_format.format(bytes);
+        // folder sizes are only known once worked out, so the total is a lower bound until then
+        if unknown > 0 {
+            ui.label(format!("Total Size: at least {}", size));
+            ui.weak(format!("{} without a known size", unknown));
+        } else {
+            ui.label(format!("Total Size: {}", size));
+        }
     }
 
     fn build_top_panel(&mut self, ui: &mut egui::Ui) {
@@ -215,35 +1272,255 @@ impl FileNewerGui {
         if new_path.lost_focus() || ui.input(|i| i.key_pressed(egui::Key::Enter)) {
             self.update_working_dir();
         }
+        if ui.button("🔍 Search").on_hover_text("Find files by name anywhere below this folder").clicked() {
+            self.name_search.open = true;
+        }
+        if ui.button("Search Inside Files").on_hover_text("Find files containing some text below this folder")
+            .clicked() {
+            self.content_search.open = true;
+        }
+    }
+
+    fn build_content_search(&mut self, ui: &mut egui::Ui) {
+        let mut start = false;
+        let query = &mut self.content_search.query;
+        ui.horizontal(|ui| {
+            let text = ui.add(egui::TextEdit::singleline(&mut query.pattern)
+                .hint_text(if query.regex { "fn \\w+\\(" } else { "text to find" })
+                .desired_width(200.0));
+            start = text.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            ui.toggle_value(&mut query.regex, ".*").on_hover_text("Regular expression");
+            ui.toggle_value(&mut query.case_sensitive, "Aa").on_hover_text("Match case");
+            ui.label("Context");
+            ui.add(egui::DragValue::new(&mut query.context).clamp_range(0..=10));
+        });
+        ui.horizontal(|ui| {
+            if self.content_search.search.is_some() {
+                ui.spinner();
+                if ui.button("Stop").clicked() { self.content_search.search = None; }
+            } else {
+                start |= ui.button("Search").clicked();
+            }
+            ui.label(format!("under {}", self.user_facing_path));
+        });
+        let panel = &self.content_search;
+        ui.horizontal(|ui| {
+            let lines: usize = panel.hits.iter().map(|hit| hit.matches.len()).sum();
+            ui.label(format!("{} lines in {} files", lines, panel.hits.len()));
+            if panel.unreadable > 0 { ui.label(format!("{} unreadable", panel.unreadable)); }
+            if let Some(error) = &panel.error { ui.colored_label(ui.visuals().error_fg_color, error); }
+        });
+        if start { self.start_content_search(); }
+        ui.separator();
+
+        let panel = &self.content_search;
+        let mut open_file = None;
+        let mut reveal = None;
+        egui::ScrollArea::vertical().auto_shrink(false).show(ui, |ui| {
+            for hit in &panel.hits {
+                let path = panel.root.join(&hit.relative_path);
+                ui.horizontal(|ui| {
+                    ui.strong(hit.relative_path.to_string_lossy());
+                    if ui.small_button("Open").clicked() { open_file = Some(path.clone()); }
+                    if ui.small_button("Show in Folder").clicked() { reveal = Some(path.clone()); }
+                });
+                for line_match in &hit.matches {
+                    let first = line_match.line_number - line_match.before.len();
+                    for (offset, line) in line_match.before.iter().enumerate() {
+                        ui.weak(egui::RichText::new(format!("{:>6}  {}", first + offset, line)).monospace());
+                    }
+                    ui.monospace(format!("{:>6}: {}", line_match.line_number, line_match.line));
+                    for (offset, line) in line_match.after.iter().enumerate() {
+                        ui.weak(egui::RichText::new(format!("{:>6}  {}", line_match.line_number + 1 + offset, line))
+                            .monospace());
+                    }
+                    if !line_match.before.is_empty() || !line_match.after.is_empty() { ui.add_space(4.0); }
+                }
+                ui.separator();
+            }
+        });
+        if let Some(path) = open_file {
+            if let Err(e) = open::that_detached(&path) {
+                self.error_message = Some(format!("Cannot open {}, as {}", path.display(), e));
+            }
+        }
+        if let Some(path) = reveal { self.reveal_path(path); }
+    }
+
+    fn build_search(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            name_filter_ui(ui, &mut self.name_search.filter, "search_type_filter");
+        });
+        let mut start = false;
+        ui.horizontal(|ui| {
+            if self.name_search.search.is_some() {
+                ui.spinner();
+                if ui.button("Stop").clicked() { self.name_search.search = None; }
+            } else {
+                start = ui.button("Search").clicked();
+            }
+            ui.label(format!("under {}", self.user_facing_path));
+        });
+        let panel = &mut self.name_search;
+        ui.horizontal(|ui| {
+            ui.label(format!("{} found", panel.hits.len()));
+            if panel.unreadable > 0 { ui.label(format!("{} folders unreadable", panel.unreadable)); }
+            if let Some(error) = &panel.error { ui.colored_label(ui.visuals().error_fg_color, error); }
+        });
+        if start { self.start_search(); }
+
+        let panel = &mut self.name_search;
+        let mut reveal = false;
+        ui.add_enabled_ui(panel.selected.is_some(), |ui| {
+            reveal = ui.button("Go to Folder").clicked();
+        });
+        ui.separator();
+        ui.push_id("search_results", |ui| {
+            TableBuilder::new(ui)
+                .striped(true)
+                .resizable(true)
+                .sense(egui::Sense::click())
+                .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                .column(TableColumn::exact(10.0))
+                .column(TableColumn::remainder().clip(true).at_least(200.0))
+                .column(TableColumn::auto().at_least(60.0))
+                .column(TableColumn::auto().at_least(100.0))
+                .header(20.0, |mut header| {
+                    header.col(|_| {});
+                    header.col(|ui| { ui.strong("Path"); });
+                    header.col(|ui| { ui.strong("File Size"); });
+                    header.col(|ui| { ui.strong("Modified Date"); });
+                })
+                .body(|body| {
+                    body.rows(18.0, panel.hits.len(), |mut row| {
+                        let hit = &panel.hits[row.index()];
+                        row.set_selected(panel.selected == Some(row.index()));
+                        row.col(|ui| { ui.label(hit.file.single_char_desc()); });
+                        row.col(|ui| { ui.label(hit.relative_path.to_string_lossy()); });
+                        row.col(|ui| { ui.label(hit.file.size_formated(&self.display_options.size_format)); });
+                        row.col(|ui| { ui.label(hit.file.last_mod_formated(&self.display_options.date_format)); });
+                        let response = row.response();
+                        if response.clicked() { panel.selected = Some(row.index()); }
+                        if response.double_clicked() { reveal = true; }
+                    });
+                });
+        });
+        let target = self.name_search.selected
+            .and_then(|idx| self.name_search.hits.get(idx))
+            .map(|hit| self.name_search.root.join(&hit.relative_path));
+        if let (true, Some(target)) = (reveal, target) { self.reveal_path(target); }
+    }
+
+    fn build_trash(&mut self, ui: &mut egui::Ui) {
+        let mut restore = false;
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() { self.refresh_trash(); }
+            ui.add_enabled_ui(self.trash.selected.is_some(), |ui| {
+                restore = ui.button("Restore").clicked();
+            });
+            ui.label(format!("{} items", self.trash.items.len()));
+            if self.trash.unreadable > 0 { ui.label(format!("{} unreadable", self.trash.unreadable)); }
+        });
+        ui.separator();
+        let panel = &mut self.trash;
+        ui.push_id("trash_items", |ui| {
+            TableBuilder::new(ui)
+                .striped(true)
+                .resizable(true)
+                .sense(egui::Sense::click())
+                .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+                .column(TableColumn::auto().at_least(120.0).clip(true))
+                .column(TableColumn::remainder().clip(true).at_least(200.0))
+                .column(TableColumn::auto().at_least(100.0))
+                .header(20.0, |mut header| {
+                    header.col(|ui| { ui.strong("Name"); });
+                    header.col(|ui| { ui.strong("Original Location"); });
+                    header.col(|ui| { ui.strong("Deleted"); });
+                })
+                .body(|body| {
+                    body.rows(18.0, panel.items.len(), |mut row| {
+                        let item = &panel.items[row.index()];
+                        row.set_selected(panel.selected == Some(row.index()));
+                        row.col(|ui| {
+                            ui.label(item.original_path.file_name().unwrap_or_default().to_string_lossy());
+                        });
+                        row.col(|ui| {
+                            let parent = item.original_path.parent().unwrap_or(&item.original_path);
+                            ui.label(parent.to_string_lossy());
+                        });
+                        row.col(|ui| { ui.label(self.display_options.date_format.format(item.deleted)); });
+                        if row.response().clicked() { panel.selected = Some(row.index()); }
+                    });
+                });
+        });
+        if restore { self.restore_selected_trash(); }
+    }
+
+    // opens a directory, or the folder holding a file with that file selected
+    fn go_to_path(&mut self, target: PathBuf) {
+        if target.is_dir() {
+            self.user_facing_path = target.to_string_lossy().into_owned();
+            self.update_working_dir();
+        } else { self.reveal_path(target); }
+    }
+
+    // opens the folder holding target, with target selected
+    fn reveal_path(&mut self, target: PathBuf) {
+        let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+            self.error_message = Some(format!("Cannot open folder of {}", target.display()));
+            return;
+        };
+        self.user_facing_path = dir.to_string_lossy().into_owned();
+        let name = name.to_os_string();
+        self.update_working_dir();
+        self.select_on_load = Some(name);
     }
 
     fn update_working_dir(&mut self) {
         let path = match evaluate_path_vars(&self.user_facing_path) {
             Ok(path) => path,
             Err(e) => {
-                self.error_message = Some(format!("{}", e));
+                self.error_message = Some(format!("Cannot open folder, as {}", e));
                 return;
             }
         };
 
         if !check_dir_exists(&path) {
-            self.error_message = Some(format!("Cannot open folder, as cannot find {}", path));
+            self.error_message = Some(format!("Cannot open folder, as cannot find {}", path.display()));
             return;
         }
 
-        match get_files_in_dir(&path, &self.display_options.show_hidden) {
-            Ok(mut files) => {
-                self.display_options.sort(&mut files);
-                self.user_facing_path = path.clone();
-                self.files_in_cur_path = files;
-            },
-            Err(e) => {
-                self.error_message = Some(format!("Cannot read contents of folder as {}", e));
-            }
+        self.user_facing_path = path.to_string_lossy().into_owned();
+        self.load_dir(path);
+    }
+
+    // starts reading path into the table, starting from nothing selected
+    fn load_dir(&mut self, path: PathBuf) {
+        // replacing the loader drops, and so cancels, whatever was still loading
+        self.loader = Some(DirLoader::spawn(path.clone(), self.display_options.show_hidden));
+        // watch before the listing is read so nothing created in between is missed
+        if self.watcher.as_ref().map(DirWatcher::path) != Some(path.as_path()) {
+            self.watcher = DirWatcher::watch(&path).ok();
         }
+        if path != self.cur_path { self.inline_rename = None; }
+        self.cur_path = path;
+        self.files_in_cur_path.clear();
+        self.listing_errors.clear();
+        self.selection.clear();
+        self.select_on_load = None;
+        self.reselect_on_load = None;
+        self.load_cancelled = false;
+        self.dir_size_jobs.clear();
+        self.dir_sizes_pending.clear();
     }
 
     fn build_files_table(&mut self, ui: &mut egui::Ui) {
+        let columns = self.display_options.columns();
+        // the table keeps its widths by position, so give every layout its own and seed it from ours
+        ui.push_id(&columns, |ui| self.build_table_columns(ui, &columns));
+    }
+
+    fn build_table_columns(&mut self, ui: &mut egui::Ui, columns: &[Column]) {
         let height_available = ui.available_height();
         let mut table = TableBuilder::new(ui)
             .resizable(true)
@@ -252,77 +1529,284 @@ impl FileNewerGui {
             .min_scrolled_height(0.0)
             .max_scroll_height(height_available);
 
-        table = table.column(Column::exact(10.0)) // File Icon
-            .column(Column::auto().clip(true).at_least(80.0)) // File Name
-            .column(Column::auto().clip(true).at_least(40.0)) // File type
-            .column(Column::auto().clip(true).at_least(100.0)) // File creation date
-            .column(Column::auto().clip(true).at_least(100.0)) // File last edit date
-            .column(Column::auto().clip(true).at_least(40.0)); //File Size
+        for column in columns {
+            let min_width = match column {
+                Column::Type => { 10.0 }
+                Column::Ext | Column::Size => { 40.0 }
+                Column::Owner | Column::Group => { 50.0 }
+                Column::Name | Column::Permissions => { 80.0 }
+                Column::Created | Column::Modified | Column::Accessed => { 100.0 }
+            };
+            let table_column = match self.column_widths.get(column) {
+                Some(width) => TableColumn::initial(*width),
+                None => TableColumn::auto(),
+            };
+            table = table.column(table_column.clip(true).at_least(min_width));
+        }
 
         table = table.sense(egui::Sense::click());
+        let mut go_to = None;
+        let mut sort_clicked = None;
+        let mut moved = None;
+        let mut context_action = None;
+        let mut context_new = None;
+        let mut rename_finished = None;
+        let mut widths = Vec::new();
 
         table
             .header(20.0, |mut header| {
-                header.col(|ui| { ui.strong(""); }); // Icon
-                header.col(|ui| { ui.strong("File Name"); });
-                header.col(|ui| { ui.strong("File Type"); });
-                header.col(|ui| { ui.strong("Creation Date"); });
-                header.col(|ui| { ui.strong("Modified Date"); });
-                header.col(|ui| { ui.strong("File Size"); });
+                for column in columns.iter().copied() {
+                    let (_, response) = header.col(|ui| {
+                        let id = egui::Id::new(("column header", column.key()));
+                        let drag = ui.dnd_drag_source(id, column, |ui| self.sort_header(ui, column));
+                        if drag.inner.clicked() {
+                            sort_clicked = Some((column, ui.input(|i| i.modifiers.shift)));
+                        }
+                    });
+                    if let Some(dragged) = response.dnd_release_payload::<Column>() {
+                        moved = Some((*dragged, column));
+                    }
+                }
             })
             .body(|body| {
+                widths = body.widths().to_vec();
                 const ROW_HEIGHT: f32 = 18.0;
-                body.rows(ROW_HEIGHT, self.files_in_cur_path.len(), |mut row| {
-                    let row_index = row.index();
+                body.rows(ROW_HEIGHT, self.visible_rows.len(), |mut row| {
+                    let row_index = self.visible_rows[row.index()];
                     let file = &self.files_in_cur_path[row_index];
 
-                    row.col(|ui| {
-                        ui.label(file.single_char_desc());
-                    });
-                    row.col(|ui| {
-                        ui.label(match file.file_name.to_str() {
-                            Some(s) => s.to_string(),
-                            None => "!!ERROR!!".to_string(),
+                    for column in columns.iter().copied() {
+                        row.col(|ui| {
+                            let renaming = self.inline_rename.as_mut()
+                                .filter(|rename| column == Column::Name && rename.name == file.file_name);
+                            if let Some(rename) = renaming {
+                                if let Some(confirmed) = inline_rename_ui(ui, rename) { rename_finished = Some(confirmed); }
+                                return;
+                            }
+                            let text = file.column_text(column, &self.display_options);
+                            if column == Column::Permissions { ui.monospace(text); } else { ui.label(text); }
                         });
-                    });
-                    row.col(|ui| { ui.label(file.file_ext.as_ref().unwrap_or(&"".to_string())); });
-                    row.col(|ui| {
-                        ui.label(file.creation_time_formated());
-                    });
-                    row.col(|ui| {
-                        ui.label(file.last_mod_formated());
-                    });
-
-                    row.col(|ui| {
-                        ui.label(
-                            if !file.is_dir(){format!("{}", file.file_size)}else {"-".to_owned()});
-                    });
+                    }
 
                     let rr = row.response();
-                    if rr.clicked(){
-                        if self.selected_file == Some(row_index) {
+                    let name = file.file_name.as_os_str();
+                    // right clicking inside the selection keeps it, so the menu acts on all of it
+                    if rr.secondary_clicked() && !self.selection.contains(name) { self.selection.select_only(name); }
+                    rr.context_menu(|ui| {
+                        if let Some(new) = self.new_entry_buttons(ui) { context_new = Some(new); }
+                        ui.separator();
+                        if let Some(action) = self.file_action_buttons(ui) { context_action = Some(action); }
+                    });
+                    let modifiers = rr.ctx.input(|i| i.modifiers);
+                    if rr.clicked() && modifiers.command { self.selection.toggle(name); }
+                    else if rr.clicked() && modifiers.shift {
+                        self.selection.select_range(shown_names(&self.files_in_cur_path, &self.visible_rows), name);
+                    }
+                    else if rr.clicked(){
+                        if self.selection.len() == 1 && self.selection.contains(name) {
                             if file.is_dir() {
-                                self.user_facing_path.push_str(file.file_name.to_str().unwrap());
-                                self.user_facing_path.push_str(&*"\\");
+                                let pth = self.cur_path.join(&file.file_name);
+                                self.user_facing_path = pth.to_string_lossy().into_owned();
                                 self.update_files_this_loop = true;
                             }
+                            else if file.is_link() {
+                                go_to = file.resolve_link_target(&self.cur_path);
+                            }
                             else {
-                                let mut pth = PathBuf::from(&self.user_facing_path);
-                                pth.push(&file.file_name);
-                                let _ = open::that_detached(pth);
+                                let pth = self.cur_path.join(&file.file_name);
+                                if let Err(e) = open::that_detached(&pth) {
+                                    self.error_message = Some(format!("Cannot open {}, as {}", pth.display(), e));
+                                }
                             }
                                 //self.error_message = Some("Currently not supported".to_string());}
                         }
-                        else{ self.selected_file = Some(row_index); }
+                        else{ self.selection.select_only(name); }
                     }
-                    row.set_selected(self.selected_file == Some(row_index));
+                    row.set_selected(self.selection.contains(name));
                 });
             });
+        // the space under the rows gets the New entries too, so they can be reached in an empty folder
+        let background = ui.allocate_rect(ui.available_rect_before_wrap(), egui::Sense::click());
+        background.context_menu(|ui| {
+            if let Some(new) = self.new_entry_buttons(ui) { context_new = Some(new); }
+        });
+        self.column_widths.extend(columns.iter().copied().zip(widths));
+        if let Some(target) = go_to { self.go_to_path(target); }
+        if let Some((column, secondary)) = sort_clicked {
+            self.display_options.toggle_sort(column.sort_by(), secondary);
+            self.sort_files();
+        }
+        if let Some((column, target)) = moved { self.display_options.move_column(column, target); }
+        if let Some(action) = context_action { self.run_action(action); }
+        if let Some(new) = context_new { self.create_entry(new); }
+        if let Some(confirmed) = rename_finished { self.finish_inline_rename(confirmed); }
+    }
+
+    // a header label with an arrow when the column is sorted on, numbered for tie breakers
+    fn sort_header(&self, ui: &mut egui::Ui, column: Column) -> egui::Response {
+        let arrow = match self.display_options.sort_position(&column.sort_by()) {
+            Some((pos, descending)) => {
+                let arrow = if descending { "⏷" } else { "⏶" };
+                if pos == 0 { format!(" {}", arrow) } else { format!(" {}{}", arrow, pos + 1) }
+            }
+            None => String::new(),
+        };
+        ui.add(egui::Label::new(egui::RichText::new(format!("{}{}", column.header(), arrow)).strong())
+            .sense(egui::Sense::click()))
+            .on_hover_text("Click to sort, Shift click to sort by this as well, drag to move")
+    }
+
+    // the file operation buttons, shared by the File menu and the context menu, returns the one clicked
+    fn file_action_buttons(&self, ui: &mut egui::Ui) -> Option<FileAction> {
+        let actions = [
+            (FileAction::Copy, "Copy", "Ctrl+C"),
+            (FileAction::Cut, "Cut", "Ctrl+X"),
+            (FileAction::Paste, "Paste", "Ctrl+V"),
+            (FileAction::Rename, "Rename", "F2"),
+            (FileAction::Trash, "Move to Trash", "Del"),
+            (FileAction::Delete, "Delete Permanently", "Shift+Del"),
+        ];
+        let mut clicked = None;
+        for (action, label, shortcut) in actions {
+            clicked = clicked.or(self.action_button(ui, action, label, shortcut));
+        }
+        clicked
+    }
+
+    // New Folder, New File and a submenu of the templates, shared like file_action_buttons
+    fn new_entry_buttons(&self, ui: &mut egui::Ui) -> Option<NewEntry> {
+        let mut clicked = None;
+        if ui.add(egui::Button::new("New Folder").shortcut_text("Ctrl+Shift+N")).clicked() {
+            clicked = Some(NewEntry::Folder);
+        }
+        if ui.button("New File").clicked() { clicked = Some(NewEntry::File); }
+        ui.menu_button("New from Template", |ui| {
+            // read as the menu opens so templates added meanwhile show up
+            let templates = templates_dir().map(|dir| list_templates(&dir)).unwrap_or_default();
+            if templates.is_empty() { ui.weak("No templates found"); }
+            for template in templates {
+                let name = template.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+                if ui.button(name).clicked() { clicked = Some(NewEntry::FromTemplate(template)); }
+            }
+        });
+        if clicked.is_some() { ui.close_menu(); }
+        clicked
+    }
+
+    // a menu button for action, greyed out when it can not run
+    fn action_button(&self, ui: &mut egui::Ui, action: FileAction, label: &str, shortcut: &str) -> Option<FileAction> {
+        let button = egui::Button::new(label).shortcut_text(shortcut);
+        if ui.add_enabled(self.can_run(action), button).clicked() {
+            ui.close_menu();
+            return Some(action);
+        }
+        None
+    }
+
+    // oldest at the top, what has been undone greyed out below what can still be undone
+    fn build_history(&mut self, ui: &mut egui::Ui) {
+        let mut clicked = None;
+        ui.horizontal(|ui| {
+            if ui.add_enabled(self.can_run(FileAction::Undo), egui::Button::new("Undo")).clicked() {
+                clicked = Some(FileAction::Undo);
+            }
+            if ui.add_enabled(self.can_run(FileAction::Redo), egui::Button::new("Redo")).clicked() {
+                clicked = Some(FileAction::Redo);
+            }
+        });
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if self.journal.next_undo().is_none() && self.journal.next_redo().is_none() {
+                ui.weak("Nothing done yet");
+            }
+            for entry in self.journal.done() {
+                ui.label(&entry.label);
+            }
+            for entry in self.journal.undone().iter().rev() {
+                ui.weak(&entry.label);
+            }
+        });
+        if let Some(action) = clicked { self.run_action(action); }
+    }
+
+    fn build_filter_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Filter");
+            if name_filter_ui(ui, &mut self.name_filter, "type_filter") { self.compile_filter(); }
+            if let Some(error) = &self.filter_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+        });
     }
 
     fn build_main_frame(&mut self, ui: &mut egui::Ui) {
+        self.build_filter_bar(ui);
         egui::ScrollArea::horizontal().show(ui, |ui| {
             self.build_files_table(ui);
         });
     }
-}
\ No newline at end of file
+}
+
+// the names of the entries shown, in order, what Select All and the like work on
+fn shown_names<'a>(files: &'a [FileInfo], rows: &'a [usize]) -> impl Iterator<Item = &'a OsStr> {
+    rows.iter().map(|idx| files[*idx].file_name.as_os_str())
+}
+
+// `Rename notes.txt` for one entry, `Copy 3 items` for several
+fn op_label(verb: &str, paths: &[PathBuf]) -> String {
+    match paths {
+        [path] => format!("{} {}", verb, path.file_name().unwrap_or_default().to_string_lossy()),
+        _ => format!("{} {} items", verb, paths.len()),
+    }
+}
+
+// the name editor shown in place of an entry's name, Some once it is confirmed or, with false, cancelled
+fn inline_rename_ui(ui: &mut egui::Ui, rename: &mut InlineRename) -> Option<bool> {
+    let output = egui::TextEdit::singleline(&mut rename.new_name).desired_width(f32::INFINITY).show(ui);
+    if !rename.focused {
+        rename.focused = true;
+        output.response.request_focus();
+        // the name but not the extension is selected, ready to type over
+        let stem_len = std::path::Path::new(&rename.new_name).file_stem()
+            .map_or(0, |stem| stem.to_string_lossy().chars().count());
+        let mut state = output.state;
+        state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+            egui::text::CCursor::new(0), egui::text::CCursor::new(stem_len))));
+        state.store(ui.ctx(), output.response.id);
+    }
+    if !output.response.lost_focus() { return None; }
+    Some(!ui.input(|i| i.key_pressed(egui::Key::Escape)))
+}
+
+// the pattern, match mode, case and type controls, returns whether any of them changed
+fn name_filter_ui(ui: &mut egui::Ui, filter: &mut NameFilter, id_source: &str) -> bool {
+    let mut changed = ui.add(egui::TextEdit::singleline(&mut filter.pattern)
+        .hint_text(match filter.mode {
+            MatchMode::Substring => { "part of a name" }
+            MatchMode::Glob => { "*.rs" }
+            MatchMode::Regex => { "^main\\.(rs|c)$" }
+            MatchMode::Fuzzy => { "flmg" }
+        })
+        .desired_width(200.0)).changed();
+    if !filter.pattern.is_empty() && ui.small_button("✖").clicked() {
+        filter.pattern.clear();
+        changed = true;
+    }
+    changed |= ui.radio_value(&mut filter.mode, MatchMode::Substring, "Text").changed();
+    changed |= ui.radio_value(&mut filter.mode, MatchMode::Glob, "Glob").changed();
+    changed |= ui.radio_value(&mut filter.mode, MatchMode::Regex, "Regex").changed();
+    changed |= ui.radio_value(&mut filter.mode, MatchMode::Fuzzy, "Fuzzy").changed();
+    changed |= ui.toggle_value(&mut filter.case_sensitive, "Aa").on_hover_text("Match case").changed();
+    egui::ComboBox::from_id_source(id_source)
+        .selected_text(match filter.types {
+            TypeFilter::All => { "Everything" }
+            TypeFilter::FilesOnly => { "Files Only" }
+            TypeFilter::DirsOnly => { "Folders Only" }
+        })
+        .show_ui(ui, |ui| {
+            changed |= ui.selectable_value(&mut filter.types, TypeFilter::All, "Everything").changed();
+            changed |= ui.selectable_value(&mut filter.types, TypeFilter::FilesOnly, "Files Only").changed();
+            changed |= ui.selectable_value(&mut filter.types, TypeFilter::DirsOnly, "Folders Only").changed();
+        });
+    changed
+}
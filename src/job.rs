@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::thread;
+
+/// Work running on its own thread, sending events back to be polled from the UI.
+///
+/// The worker is handed a flag it should check as it goes and stop once set. Dropping the job
+/// sets it, so replacing a job is enough to abandon the old one.
+pub(crate) struct Job<T> {
+    events: Receiver<T>,
+    cancelled: Arc<AtomicBool>,
+    // whether an event is the last one the worker sends
+    is_last: fn(&T) -> bool,
+    finished: bool,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub(crate) fn spawn(is_last: fn(&T) -> bool, work: impl FnOnce(Sender<T>, Arc<AtomicBool>) + Send + 'static)
+        -> Self {
+        let (sender, events) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let worker_cancelled = cancelled.clone();
+        thread::spawn(move || work(sender, worker_cancelled));
+        Self { events, cancelled, is_last, finished: false }
+    }
+
+    /// Everything the worker has sent since the last poll, never blocks
+    pub(crate) fn poll(&mut self) -> Vec<T> {
+        let mut events = Vec::new();
+        while !self.finished {
+            match self.events.try_recv() {
+                Ok(event) => {
+                    self.finished = (self.is_last)(&event);
+                    events.push(event);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.finished = true,
+            }
+        }
+        events
+    }
+
+    /// `true` once the last event has been polled, the worker has gone or the job was cancelled
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Asks the worker to stop but keeps polling, for workers that report what they got done before stopping
+    pub(crate) fn stop(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Asks the worker to stop, nothing more is polled after this
+    pub(crate) fn cancel(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.finished = true;
+    }
+}
+
+impl<T> Drop for Job<T> {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,263 @@
+//! A record of file operations so they can be undone and redone.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::error::FileNewerError;
+use crate::file_ops::{copy_recursive, delete_entry, move_path};
+use crate::trash;
+use crate::trash::{restore, trash_entry, trash_entry_in, TrashItem};
+use crate::walk::Walk;
+
+// older entries are forgotten past this, so the journal can not grow for ever
+const MAX_ENTRIES: usize = 100;
+
+/// A file operation that was carried out, with what is needed to reverse it
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub enum FileOp {
+    /// Renamed or moved, the two are the same thing on disk
+    Move {
+        /// Where the entry was
+        from: PathBuf,
+        /// Where it is now
+        to: PathBuf,
+    },
+    /// `to` was made as a copy of `from`, with its size and modification time once copied.
+    ///
+    /// A folder's size is everything inside it and its time the latest of any entry in it.
+    Copy {
+        /// What was copied
+        from: PathBuf,
+        /// The copy
+        to: PathBuf,
+        /// The size of the copy once made
+        size: u64,
+        /// When the copy was last modified once made
+        modified: Option<SystemTime>,
+    },
+    /// Moved to the trash, where it can be restored from
+    Trash(TrashItem),
+    /// A new empty folder
+    CreateDir(PathBuf),
+    /// A new empty file
+    CreateFile(PathBuf),
+}
+
+impl FileOp {
+    /// A `Copy` of `from` to `to`, to be made once `to` has been copied
+    pub fn copied(from: PathBuf, to: PathBuf) -> Result<FileOp, FileNewerError> {
+        let (size, modified) = stamp(&to)?;
+        Ok(FileOp::Copy { from, to, size, modified })
+    }
+
+    /// Reverses the operation, returning what redoes it.
+    ///
+    /// Fails without touching anything when the disk no longer looks the way the operation left it.
+    pub fn undo(self) -> Result<FileOp, FileNewerError> {
+        self.undo_in(None)
+    }
+
+    /// Carries the operation out again after an undo, returning what undoes it
+    pub fn redo(self) -> Result<FileOp, FileNewerError> {
+        self.redo_in(None)
+    }
+
+    // undo with the home trash given, None for the user's own
+    fn undo_in(self, home_trash: Option<&Path>) -> Result<FileOp, FileNewerError> {
+        match self {
+            FileOp::Move { from, to } => {
+                expect_free(&from)?;
+                move_path(&to, &from)?;
+                Ok(FileOp::Move { from, to })
+            }
+            FileOp::Copy { from, to, size, modified } => {
+                // anything done to the copy since would be lost along with it
+                if stamp(&to)? != (size, modified) {
+                    return Err(FileNewerError::Io { path: to, source: io::Error::other("it has changed since it was copied") });
+                }
+                // into the trash where it can, in case it was wanted after all
+                if trash::SUPPORTED { trash_in(home_trash, &to)?; } else { delete_entry(&to)?; }
+                Ok(FileOp::Copy { from, to, size, modified })
+            }
+            FileOp::Trash(item) => {
+                restore(&item)?;
+                Ok(FileOp::Trash(item))
+            }
+            FileOp::CreateDir(path) => {
+                // only an empty folder, anything put in it since would be lost
+                fs::remove_dir(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+                Ok(FileOp::CreateDir(path))
+            }
+            FileOp::CreateFile(path) => {
+                let meta = fs::symlink_metadata(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+                // like a folder, it is only removed while there is nothing in it to lose
+                if meta.len() > 0 {
+                    return Err(FileNewerError::Io { path, source: io::Error::other("it has been written to since") });
+                }
+                fs::remove_file(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+                Ok(FileOp::CreateFile(path))
+            }
+        }
+    }
+
+    fn redo_in(self, home_trash: Option<&Path>) -> Result<FileOp, FileNewerError> {
+        match self {
+            FileOp::Move { from, to } => {
+                expect_free(&to)?;
+                move_path(&from, &to)?;
+                Ok(FileOp::Move { from, to })
+            }
+            FileOp::Copy { from, to, .. } => {
+                expect_free(&to)?;
+                copy_recursive(&from, &to)?;
+                FileOp::copied(from, to)
+            }
+            // it lands somewhere new in the trash, so the item to restore changes
+            FileOp::Trash(item) => { trash_in(home_trash, &item.original_path).map(FileOp::Trash) }
+            FileOp::CreateDir(path) => {
+                fs::create_dir(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+                Ok(FileOp::CreateDir(path))
+            }
+            FileOp::CreateFile(path) => {
+                OpenOptions::new().write(true).create_new(true).open(&path)
+                    .map_err(|e| FileNewerError::from_io(&path, e))?;
+                Ok(FileOp::CreateFile(path))
+            }
+        }
+    }
+}
+
+fn expect_free(path: &Path) -> Result<(), FileNewerError> {
+    if fs::symlink_metadata(path).is_ok() { Err(FileNewerError::AlreadyExists(path.to_path_buf())) } else { Ok(()) }
+}
+
+fn trash_in(home_trash: Option<&Path>, path: &Path) -> Result<TrashItem, FileNewerError> {
+    match home_trash {
+        Some(home_trash) => { trash_entry_in(home_trash, path) }
+        None => { trash_entry(path) }
+    }
+}
+
+// the total size of path and the last time anything in it changed, links are not followed
+fn stamp(path: &Path) -> Result<(u64, Option<SystemTime>), FileNewerError> {
+    let meta = fs::symlink_metadata(path).map_err(|e| FileNewerError::from_io(path, e))?;
+    if !meta.is_dir() { return Ok((meta.len(), meta.modified().ok())); }
+    let mut size = 0;
+    let mut modified = meta.modified().ok();
+    for entry in Walk::new(path, true)? {
+        let meta = entry?.meta;
+        if !meta.is_dir() { size += meta.len(); }
+        modified = modified.max(meta.modified().ok());
+    }
+    Ok((size, modified))
+}
+
+/// Operations done together, like every entry of one paste, undone and redone as one
+#[derive(Debug)]
+pub struct JournalEntry {
+    /// What the user did, such as `Rename notes.txt`
+    pub label: String,
+    /// The operations, in the order they were done
+    pub ops: Vec<FileOp>,
+}
+
+/// How an undo or redo went
+#[derive(Default)]
+#[derive(Debug)]
+pub struct Replay {
+    /// Why each operation that could not be carried out failed
+    pub errors: Vec<FileNewerError>,
+    /// Whether those operations were dropped from the history because the rest of the entry went through
+    pub dropped: bool,
+}
+
+/// The file operations done so far, most recent last, and those undone that can be redone
+#[derive(Default)]
+#[derive(Debug)]
+pub struct Journal {
+    done: VecDeque<JournalEntry>,
+    undone: Vec<JournalEntry>,
+    // where undoing a copy trashes it, None for the user's own trash
+    home_trash: Option<PathBuf>,
+}
+
+impl Journal {
+    // a journal that trashes into home_trash, so tests leave the user's trash alone
+    #[cfg(test)]
+    pub(crate) fn with_home_trash(home_trash: PathBuf) -> Self {
+        Self { home_trash: Some(home_trash), ..Self::default() }
+    }
+
+    /// Adds what was just done, anything undone can no longer be redone after this
+    pub fn record(&mut self, label: String, ops: Vec<FileOp>) {
+        if ops.is_empty() { return; }
+        self.done.push_back(JournalEntry { label, ops });
+        if self.done.len() > MAX_ENTRIES { self.done.pop_front(); }
+        self.undone.clear();
+    }
+
+    /// Undoes the most recent entry, newest operation first.
+    ///
+    /// If only some operations can be undone the rest are dropped and the entry can be redone
+    /// without them. If none can, the entry is left where it was to be tried again.
+    pub fn undo(&mut self) -> Replay {
+        let Some(entry) = self.done.pop_back() else { return Replay::default() };
+        let (ops, errors) = apply(entry.ops.iter().rev().cloned(), |op| op.undo_in(self.home_trash.as_deref()));
+        if ops.is_empty() && !errors.is_empty() {
+            self.done.push_back(entry);
+            return Replay { errors, dropped: false };
+        }
+        self.undone.push(JournalEntry { label: entry.label, ops: ops.into_iter().rev().collect() });
+        Replay { dropped: !errors.is_empty(), errors }
+    }
+
+    /// Redoes the most recently undone entry, the same way `undo` undoes one
+    pub fn redo(&mut self) -> Replay {
+        let Some(entry) = self.undone.pop() else { return Replay::default() };
+        let (ops, errors) = apply(entry.ops.iter().cloned(), |op| op.redo_in(self.home_trash.as_deref()));
+        if ops.is_empty() && !errors.is_empty() {
+            self.undone.push(entry);
+            return Replay { errors, dropped: false };
+        }
+        self.done.push_back(JournalEntry { label: entry.label, ops });
+        Replay { dropped: !errors.is_empty(), errors }
+    }
+
+    /// What `undo` would undo
+    pub fn next_undo(&self) -> Option<&JournalEntry> {
+        self.done.back()
+    }
+
+    /// What `redo` would redo
+    pub fn next_redo(&self) -> Option<&JournalEntry> {
+        self.undone.last()
+    }
+
+    /// Everything that can be undone, oldest first
+    pub fn done(&self) -> impl DoubleEndedIterator<Item = &JournalEntry> {
+        self.done.iter()
+    }
+
+    /// Everything that can be redone, the next to redo last
+    pub fn undone(&self) -> &[JournalEntry] {
+        &self.undone
+    }
+}
+
+fn apply(ops: impl Iterator<Item = FileOp>, mut step: impl FnMut(FileOp) -> Result<FileOp, FileNewerError>)
+    -> (Vec<FileOp>, Vec<FileNewerError>) {
+    let mut applied = Vec::new();
+    let mut errors = Vec::new();
+    for op in ops {
+        match step(op) {
+            Ok(op) => { applied.push(op) }
+            Err(e) => { errors.push(e) }
+        }
+    }
+    (applied, errors)
+}
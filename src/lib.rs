@@ -0,0 +1,54 @@
+//! The file model, directory listing and sorting behind the FileNewer explorer.
+//!
+//! ```no_run
+//! use filenewer::{evaluate_path_vars, DisplayOptions, SortBy};
+//!
+//! let path = evaluate_path_vars("~").unwrap();
+//! let options = DisplayOptions { sort_by: SortBy::Name, ..DisplayOptions::default() };
+//! for file in options.list(&path).unwrap().files {
+//!     println!("{} {:?}", file.single_char_desc(), file.file_name);
+//! }
+//! ```
+#![warn(missing_docs)]
+
+pub mod columns;
+pub mod content_search;
+pub mod dir_loader;
+pub mod dir_size;
+pub mod dir_watcher;
+pub mod error;
+pub mod file_job;
+pub mod file_manager;
+pub mod file_ops;
+pub mod file_ordering;
+pub mod filter;
+pub mod formatting;
+mod job;
+pub mod journal;
+pub mod ls;
+mod platform;
+pub mod search;
+pub mod selection;
+pub mod trash;
+mod tests;
+mod walk;
+
+pub use columns::{format_widths, parse_widths, Column};
+pub use content_search::{search_file, ContentEvent, ContentHit, ContentQuery, ContentSearch, LineMatch};
+pub use dir_loader::{DirLoader, LoadEvent};
+pub use dir_size::{dir_size, DirSize, DirSizeJob};
+pub use dir_watcher::{refresh_entries, DirChanges, DirWatcher};
+pub use error::FileNewerError;
+pub use file_job::{FileJob, FileJobEvent, FileTask};
+pub use file_manager::{check_dir_exists, evaluate_path_vars, format_mode,
+                       get_file_info, get_files_in_dir, normalize_path, DirListing, FileInfo, FileType};
+pub use file_ops::{conflicts, copy_entry, create_dir, create_file, delete_entry, free_name, is_valid_name,
+                   list_templates, move_entry, rename_entry, templates_dir, ConflictPolicy};
+pub use file_ordering::{DisplayOptions, CaseOrder, NameOrder, SortBy, SortKey};
+pub use filter::{MatchMode, NameFilter, NameMatcher, TypeFilter};
+pub use formatting::{DateFormat, DateStyle, SizeFormat, SizeUnits};
+pub use journal::{FileOp, Journal, JournalEntry, Replay};
+pub use ls::{parse_ls_args, LsOptions, OutputFormat, LS_USAGE};
+pub use search::{NameSearch, SearchEvent, SearchHit};
+pub use selection::Selection;
+pub use trash::{list_trash, restore, trash_entry, TrashItem};
@@ -0,0 +1,235 @@
+//! The `ls` command: reading its arguments and writing a listing out as text.
+
+use std::ffi::OsString;
+use std::fmt::Write;
+use crate::columns::Column;
+use crate::file_manager::FileInfo;
+use crate::file_ordering::{DisplayOptions, SortBy, SortKey};
+use crate::formatting::{DateStyle, SizeUnits};
+
+/// The help text printed by `FileNewer ls --help`
+pub const LS_USAGE: &str = "\
+Usage: FileNewer ls [PATH] [OPTIONS]
+
+Lists PATH (default `.`) the same way the explorer's file table does.
+
+Options:
+  --sort <KEYS>        comma separated keys out of name, type, ext, ctime, mtime, atime, size, mode,
+                       owner, group or none, later keys break ties in earlier ones
+  --desc               sort descending
+  --dirs-first         list directories before everything else
+  --hidden             include hidden files
+  --columns <KEYS>     comma separated columns out of type, name, ext, ctime, mtime, atime, size,
+                       mode, owner, group
+  --format <FORMAT>    plain, json or csv
+  --size <UNITS>       bytes, si (kB, MB) or iec (KiB, MiB), defaults to iec
+  --date <FORMAT>      standard, iso, relative or a strftime pattern such as %d/%m/%Y
+  --utc                show times in UTC rather than local time
+  -h, --help           print this message";
+
+/// How `ls` writes the listing out
+#[derive(PartialEq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum OutputFormat {
+    /// Aligned columns under a header, like the explorer's table
+    Plain,
+    /// An array of objects keyed by [`Column::key`]
+    Json,
+    /// A header row of column keys, then one row per entry
+    Csv,
+}
+
+/// Everything the arguments to `ls` asked for
+pub struct LsOptions {
+    /// The folder to list as given, it need not be valid UTF-8
+    pub path: OsString,
+    /// Sorting, formatting and whether hidden entries are listed
+    pub display_options: DisplayOptions,
+    /// The columns written out, the explorer's defaults unless `--columns` is given
+    pub columns: Vec<Column>,
+    /// How the listing is written out
+    pub format: OutputFormat,
+}
+
+impl LsOptions {
+    /// `files` written out in the chosen format and columns
+    pub fn render(&self, files: &[FileInfo]) -> String {
+        let rows: Vec<Vec<String>> = files.iter()
+            .map(|file| self.columns.iter().map(|col| file.column_text(*col, &self.display_options)).collect())
+            .collect();
+        match self.format {
+            OutputFormat::Plain => render_plain(&rows, &self.columns),
+            OutputFormat::Json => render_json(&rows, &self.columns),
+            OutputFormat::Csv => render_csv(&rows, &self.columns),
+        }
+    }
+}
+
+/// Reads the arguments that follow `ls`, `Ok(None)` when help was asked for.
+///
+/// Errors are messages to show the user along with [`LS_USAGE`].
+pub fn parse_ls_args(args: &[OsString]) -> Result<Option<LsOptions>, String> {
+    let display_options = DisplayOptions::default();
+    let mut opts = LsOptions {
+        path: OsString::from("."),
+        // the same columns the explorer starts with
+        columns: display_options.columns(),
+        display_options,
+        format: OutputFormat::Plain,
+    };
+    let mut path_given = false;
+    let mut args = args.iter();
+    while let Some(arg_os) = args.next() {
+        // anything that is not text can only be the path
+        let Some(arg) = arg_os.to_str() else {
+            if path_given { return Err(format!("unexpected argument {:?}", arg_os)); }
+            opts.path = arg_os.clone();
+            path_given = true;
+            continue;
+        };
+        let mut value = |flag: &str| match args.next() {
+            Some(value) => value.to_str().ok_or_else(|| format!("{} needs a value that is valid UTF-8", flag)),
+            None => Err(format!("{} needs a value", flag)),
+        };
+        match arg {
+            "-h" | "--help" => return Ok(None),
+            "--desc" => opts.display_options.filter_dec = true,
+            "--hidden" => opts.display_options.show_hidden = true,
+            "--dirs-first" => opts.display_options.dirs_first = true,
+            "--sort" => {
+                let mut keys = value(arg)?.split(',').map(|key| parse_sort_by(key.trim()));
+                opts.display_options.sort_by = keys.next().unwrap_or(Ok(SortBy::Nan))?;
+                opts.display_options.then_by = keys
+                    .map(|key| key.map(|by| SortKey { by, descending: false }))
+                    .collect::<Result<_, _>>()?;
+            }
+            "--columns" => opts.columns = parse_columns(value(arg)?)?,
+            "--size" => opts.display_options.size_format.units = match value(arg)? {
+                "bytes" => SizeUnits::Bytes,
+                "si" => SizeUnits::Si,
+                "iec" => SizeUnits::Iec,
+                other => return Err(format!("unknown size units `{}`", other)),
+            },
+            "--date" => {
+                let date_format = &mut opts.display_options.date_format;
+                date_format.style = match value(arg)? {
+                    "standard" => DateStyle::Standard,
+                    "iso" => DateStyle::Iso8601,
+                    "relative" => DateStyle::Relative,
+                    pattern => {
+                        date_format.custom_pattern = pattern.to_string();
+                        DateStyle::Custom
+                    }
+                };
+                if !date_format.is_valid() {
+                    return Err(format!("invalid date pattern `{}`", date_format.custom_pattern));
+                }
+            }
+            "--utc" => opts.display_options.date_format.utc = true,
+            "--format" => opts.format = match value(arg)? {
+                "plain" => OutputFormat::Plain,
+                "json" => OutputFormat::Json,
+                "csv" => OutputFormat::Csv,
+                other => return Err(format!("unknown format `{}`", other)),
+            },
+            flag if flag.starts_with('-') && flag.len() > 1 =>
+                return Err(format!("unknown option `{}`", flag)),
+            path => {
+                if path_given { return Err(format!("unexpected argument `{}`", path)); }
+                opts.path = OsString::from(path);
+                path_given = true;
+            }
+        }
+    }
+    Ok(Some(opts))
+}
+
+fn parse_sort_by(key: &str) -> Result<SortBy, String> {
+    Ok(match key {
+        "name" => SortBy::Name,
+        "type" => SortBy::Type,
+        "ext" => SortBy::Ext,
+        "ctime" => SortBy::CreateDate,
+        "mtime" => SortBy::ModDate,
+        "atime" => SortBy::ViewDate,
+        "size" => SortBy::Size,
+        "mode" => SortBy::Permissions,
+        "owner" => SortBy::Owner,
+        "group" => SortBy::Group,
+        "none" => SortBy::Nan,
+        other => return Err(format!("unknown sort key `{}`", other)),
+    })
+}
+
+fn parse_columns(keys: &str) -> Result<Vec<Column>, String> {
+    keys.split(',')
+        .map(|key| Column::from_key(key.trim())
+            .ok_or_else(|| format!("unknown column `{}`", key)))
+        .collect()
+}
+
+fn render_plain(rows: &[Vec<String>], columns: &[Column]) -> String {
+    let mut widths: Vec<usize> = columns.iter().map(|col| col.header().chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    let headers: Vec<String> = columns.iter().map(|col| col.header().to_string()).collect();
+    for row in std::iter::once(&headers).chain(rows) {
+        let line: Vec<String> = row.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        let _ = writeln!(out, "{}", line.join("  ").trim_end());
+    }
+    out
+}
+
+fn render_json(rows: &[Vec<String>], columns: &[Column]) -> String {
+    let objects: Vec<String> = rows.iter()
+        .map(|row| {
+            let fields: Vec<String> = columns.iter().zip(row)
+                .map(|(col, cell)| format!("\"{}\": {}", col.key(), json_string(cell)))
+                .collect();
+            format!("  {{{}}}", fields.join(", "))
+        })
+        .collect();
+    if objects.is_empty() { return "[]\n".to_string(); }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn render_csv(rows: &[Vec<String>], columns: &[Column]) -> String {
+    let mut out = String::new();
+    let headers: Vec<String> = columns.iter().map(|col| col.key().to_string()).collect();
+    for row in std::iter::once(&headers).chain(rows) {
+        let line: Vec<String> = row.iter().map(|cell| csv_field(cell)).collect();
+        let _ = writeln!(out, "{}", line.join(","));
+    }
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else { s.to_string() }
+}
@@ -1,16 +1,21 @@
 // hide console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+mod cli;
 mod gui;
-mod file_manager;
-mod tests;
-mod file_ordering;
 
+use std::ffi::OsString;
 use crate::gui::FileNewerGui;
 use eframe::egui;
 
 fn main() -> Result<(), eframe::Error> {
 
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
+    // args_os as a path given to ls need not be valid UTF-8
+    let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "ls") {
+        std::process::exit(cli::run_ls(&args[1..]));
+    }
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1000.0,700.0])
@@ -26,7 +31,7 @@ fn main() -> Result<(), eframe::Error> {
             // This gives us image support:
             egui_extras::install_image_loaders(&cc.egui_ctx);
 
-            Box::<FileNewerGui>::default()
+            Box::new(FileNewerGui::new(cc))
         }),
     )
 }
@@ -0,0 +1,10 @@
+// Anything that needs `std::os::*` lives behind here so the rest of the crate stays portable
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+pub(crate) use unix::*;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub(crate) use windows::*;
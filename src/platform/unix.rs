@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::env;
+use std::env::VarError;
+use std::ffi::{CStr, CString, OsString};
+use std::fs;
+use std::fs::Metadata;
+use std::io;
+use std::os::unix::ffi::OsStringExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use crate::file_manager::FileType;
+
+// on unix a file is hidden purely by convention, its name starts with a '.'
+pub(crate) fn is_hidden(file_path: &Path, _meta: &Metadata) -> bool {
+    file_path.file_name()
+        .map(|name| name.as_encoded_bytes().first() == Some(&b'.'))
+        .unwrap_or(false)
+}
+
+// birth time is only available through statx and not every filesystem records it
+pub(crate) fn creation_time(meta: &Metadata) -> Option<SystemTime> {
+    meta.created().ok()
+}
+
+// expands `~`, `~user`, `$VAR` and `${VAR}` anywhere in the path like a POSIX shell would
+// variables are looked up with var, env::var outside of tests
+pub(crate) fn evaluate_path_vars(user_facing_path: &str, var: &dyn Fn(&str) -> Result<String, VarError>)
+    -> Result<PathBuf, VarError> {
+    let path = expand_tilde(user_facing_path, var)?;
+    Ok(PathBuf::from(expand_vars(&path, var)?))
+}
+
+fn expand_tilde(path: &str, var: &dyn Fn(&str) -> Result<String, VarError>) -> Result<String, VarError> {
+    let Some(rest) = path.strip_prefix('~') else { return Ok(path.to_owned()) };
+    let (user, tail) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let home = if user.is_empty() { home_dir(var)? } else {
+        match user_home_dir(user) {
+            Some(home) => home,
+            // the shell leaves `~nobody_by_this_name` untouched so we do too
+            None => return Ok(path.to_owned())
+        }
+    };
+    Ok(format!("{}{}", home, tail))
+}
+
+fn expand_vars(path: &str, var: &dyn Fn(&str) -> Result<String, VarError>) -> Result<String, VarError> {
+    let mut out = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(idx) = rest.find('$') {
+        out.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        let (name, remaining) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", rest)
+            }
+        } else {
+            let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            out.push('$');
+            continue;
+        }
+        out.push_str(&var(name)?);
+        rest = remaining;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn home_dir(var: &dyn Fn(&str) -> Result<String, VarError>) -> Result<String, VarError> {
+    match var("HOME") {
+        Err(VarError::NotPresent) => {
+            // SAFETY: getuid can not fail
+            let uid = unsafe { libc::getuid() };
+            lookup_entry(|pwd, buf, result|
+                // SAFETY: every pointer handed to us by lookup_entry is valid for the call
+                unsafe { libc::getpwuid_r(uid, pwd, buf.as_mut_ptr(), buf.len(), result) },
+                |pwd: &libc::passwd| pwd.pw_dir)
+                .ok_or(VarError::NotPresent)
+        }
+        other => other
+    }
+}
+
+fn user_home_dir(user: &str) -> Option<String> {
+    let name = CString::new(user).ok()?;
+    lookup_entry(|pwd, buf, result|
+        // SAFETY: every pointer handed to us by lookup_entry is valid for the call
+        unsafe { libc::getpwnam_r(name.as_ptr(), pwd, buf.as_mut_ptr(), buf.len(), result) },
+        |pwd: &libc::passwd| pwd.pw_dir)
+}
+
+// runs one of the reentrant getpw*_r / getgr*_r lookups and pulls a string field out of the entry,
+// T must be libc::passwd or libc::group
+fn lookup_entry<T, F>(lookup: F, field: fn(&T) -> *mut libc::c_char) -> Option<String>
+    where F: Fn(*mut T, &mut [libc::c_char], *mut *mut T) -> libc::c_int {
+    let mut buf: Vec<libc::c_char> = vec![0; 4096];
+    // SAFETY: passwd and group are plain old data, an all zero value is valid
+    let mut entry: T = unsafe { std::mem::zeroed() };
+    let mut result: *mut T = std::ptr::null_mut();
+    loop {
+        match lookup(&mut entry, &mut buf, &mut result) {
+            libc::ERANGE => buf.resize(buf.len() * 2, 0),
+            0 if !result.is_null() => break,
+            _ => return None
+        }
+    }
+    // SAFETY: on success every string in the entry points into buf which is still alive
+    let value = unsafe { CStr::from_ptr(field(&entry)) };
+    value.to_str().ok().map(str::to_owned)
+}
+
+type NameCache = OnceLock<Mutex<HashMap<u32, String>>>;
+static USER_NAMES: NameCache = OnceLock::new();
+static GROUP_NAMES: NameCache = OnceLock::new();
+
+// a listing asks for the same few ids over and over, so each one is only looked up once
+fn cached_name(cache: &NameCache, id: u32, lookup: fn(u32) -> Option<String>) -> String {
+    let mut names = cache.get_or_init(Default::default).lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    // like ls, fall back to the number when the id has no name
+    names.entry(id).or_insert_with(|| lookup(id).unwrap_or_else(|| id.to_string())).clone()
+}
+
+fn user_name(uid: u32) -> Option<String> {
+    lookup_entry(|pwd, buf, result|
+        // SAFETY: every pointer handed to us by lookup_entry is valid for the call
+        unsafe { libc::getpwuid_r(uid, pwd, buf.as_mut_ptr(), buf.len(), result) },
+        |pwd: &libc::passwd| pwd.pw_name)
+}
+
+fn group_name(gid: u32) -> Option<String> {
+    lookup_entry(|grp, buf, result|
+        // SAFETY: every pointer handed to us by lookup_entry is valid for the call
+        unsafe { libc::getgrgid_r(gid, grp, buf.as_mut_ptr(), buf.len(), result) },
+        |grp: &libc::group| grp.gr_name)
+}
+
+pub(crate) fn mode(meta: &Metadata) -> Option<u32> {
+    Some(meta.mode())
+}
+
+// owner and group names of the entry
+pub(crate) fn ownership(meta: &Metadata) -> (Option<String>, Option<String>) {
+    (Some(cached_name(&USER_NAMES, meta.uid(), user_name)),
+     Some(cached_name(&GROUP_NAMES, meta.gid(), group_name)))
+}
+
+// fifos, sockets and devices only exist as such on unix
+pub(crate) fn special_file_type(file_type: &fs::FileType) -> Option<FileType> {
+    if file_type.is_fifo() { Some(FileType::Fifo) }
+    else if file_type.is_socket() { Some(FileType::Socket) }
+    else if file_type.is_block_device() { Some(FileType::BlockDevice) }
+    else if file_type.is_char_device() { Some(FileType::CharDevice) }
+    else { None }
+}
+
+// recreates a symlink, the target is kept exactly as stored so relative links stay relative
+pub(crate) fn symlink(target: &Path, link: &Path, _target_is_dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+pub(crate) fn is_link_loop(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(libc::ELOOP)
+}
+
+// $XDG_DATA_HOME, or ~/.local/share when it is unset or not absolute as the spec asks
+pub(crate) fn data_home() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME").map(PathBuf::from).filter(|dir| dir.is_absolute())
+        .or_else(|| home_dir(&|name| env::var(name)).ok().map(|home| Path::new(&home).join(".local/share")))
+}
+
+pub(crate) fn user_id() -> Option<u32> {
+    // SAFETY: getuid can not fail
+    Some(unsafe { libc::getuid() })
+}
+
+// the file system an entry is on, entries on the same one share an id
+pub(crate) fn device_id(meta: &Metadata) -> Option<u64> {
+    Some(meta.dev())
+}
+
+pub(crate) fn is_sticky(meta: &Metadata) -> bool {
+    meta.mode() & 0o1000 != 0
+}
+
+// where every file system is mounted, empty where /proc is not available
+pub(crate) fn mount_points() -> Vec<PathBuf> {
+    let Ok(mounts) = fs::read_to_string("/proc/self/mounts") else { return Vec::new() };
+    mounts.lines()
+        .filter_map(|line| line.split(' ').nth(1))
+        .map(|dir| PathBuf::from(unescape_mount_dir(dir)))
+        .collect()
+}
+
+// spaces and the like are written as `\040` style octal escapes
+fn unescape_mount_dir(dir: &str) -> String {
+    let mut out = String::with_capacity(dir.len());
+    let mut rest = dir;
+    while let Some(idx) = rest.find('\\') {
+        out.push_str(&rest[..idx]);
+        let code = rest.get(idx + 1..idx + 4).and_then(|oct| u8::from_str_radix(oct, 8).ok());
+        match code {
+            Some(code) => { out.push(code as char); rest = &rest[idx + 4..]; }
+            None => { out.push('\\'); rest = &rest[idx + 1..]; }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(crate) fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+// XDG_TEMPLATES_DIR from user-dirs.dirs, ~/Templates when it is not set there
+pub(crate) fn templates_dir() -> Option<PathBuf> {
+    let home = PathBuf::from(home_dir(&|name| env::var(name)).ok()?);
+    let config = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).filter(|dir| dir.is_absolute())
+        .unwrap_or_else(|| home.join(".config"));
+    templates_dir_in(&config, &home)
+}
+
+// templates_dir with the config and home folders given rather than found from the environment
+pub(crate) fn templates_dir_in(config: &Path, home: &Path) -> Option<PathBuf> {
+    let configured = fs::read_to_string(config.join("user-dirs.dirs")).ok().and_then(|dirs| {
+        dirs.lines()
+            .rev()
+            .find_map(|line| line.trim().strip_prefix("XDG_TEMPLATES_DIR="))
+            .map(|value| {
+                // the only form allowed is "$HOME/..." or an absolute path, in double quotes
+                let value = value.trim_matches('"');
+                match value.strip_prefix("$HOME") {
+                    Some(rest) => home.join(rest.trim_start_matches('/')),
+                    None => PathBuf::from(value),
+                }
+            })
+    });
+    match configured {
+        // set to the home folder itself means there is no templates folder
+        Some(dir) if dir == home => None,
+        Some(dir) => Some(dir),
+        None => Some(home.join("Templates")),
+    }
+}
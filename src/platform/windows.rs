@@ -0,0 +1,107 @@
+use std::env::VarError;
+use std::fs;
+use std::fs::Metadata;
+use std::io;
+use std::os::windows::prelude::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::file_manager::FileType;
+
+const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+
+// code is_hidden from https://users.rust-lang.org/t/read-windows-hidden-file-attribute/51180/7
+pub(crate) fn is_hidden(_file_path: &Path, meta: &Metadata) -> bool {
+    (meta.file_attributes() & FILE_ATTRIBUTE_HIDDEN) > 0
+}
+
+pub(crate) fn creation_time(meta: &Metadata) -> Option<SystemTime> {
+    meta.created().ok()
+}
+
+// `~` maps to %USERPROFILE%, a leading slash to %SYSTEMDRIVE% and a single %VAR% is expanded at the start
+// variables are looked up with var, env::var outside of tests
+pub(crate) fn evaluate_path_vars(user_facing_path: &str, var: &dyn Fn(&str) -> Result<String, VarError>)
+    -> Result<PathBuf, VarError> {
+    let win_env_path = |path: &str| -> Result<String, VarError> {
+        let mut path_str = var(path)?;
+        path_str.push('\\');
+        Ok(path_str)
+    };
+    let mut new_path =
+        user_facing_path.replace('/', "\\");
+    if new_path.starts_with('~')
+        { new_path = new_path.replacen('~', "%USERPROFILE%", 1) }
+    if new_path.starts_with('\\')
+        { new_path = new_path.replacen('\\', "%SYSTEMDRIVE%", 1) }
+
+    let mut final_path = if new_path.starts_with('%') {
+        let parts: Vec<&str> = new_path.split('%').collect();
+        if parts.len() < 2 { new_path } else {
+            let mut path = win_env_path(parts[1])?;
+            for part in &parts[2..] { path.push_str(part); }
+            path
+        }
+    }else { new_path };
+
+    // Ensure the path ends with a \\
+    if !final_path.ends_with('\\') {
+        final_path.push('\\');
+    }
+    while final_path.contains("\\\\") {
+        final_path = final_path.replace("\\\\", "\\");
+    }
+    Ok(PathBuf::from(final_path))
+}
+
+pub(crate) fn special_file_type(_file_type: &fs::FileType) -> Option<FileType> {
+    None
+}
+
+// windows has ACLs rather than mode bits and owners, neither is shown
+pub(crate) fn mode(_meta: &Metadata) -> Option<u32> {
+    None
+}
+
+pub(crate) fn ownership(_meta: &Metadata) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+// windows needs to know up front whether a link points at a directory
+pub(crate) fn symlink(target: &Path, link: &Path, target_is_dir: bool) -> io::Result<()> {
+    if target_is_dir { std::os::windows::fs::symlink_dir(target, link) }
+    else { std::os::windows::fs::symlink_file(target, link) }
+}
+
+// ERROR_CANT_RESOLVE_FILENAME, what windows says when links point at each other
+pub(crate) fn is_link_loop(e: &io::Error) -> bool {
+    e.raw_os_error() == Some(1921)
+}
+
+// there is no freedesktop trash on windows, so none of these are needed
+pub(crate) fn data_home() -> Option<PathBuf> {
+    None
+}
+
+pub(crate) fn user_id() -> Option<u32> {
+    None
+}
+
+pub(crate) fn device_id(_meta: &Metadata) -> Option<u64> {
+    None
+}
+
+pub(crate) fn is_sticky(_meta: &Metadata) -> bool {
+    false
+}
+
+pub(crate) fn mount_points() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+pub(crate) fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+pub(crate) fn templates_dir() -> Option<PathBuf> {
+    None
+}
@@ -0,0 +1,108 @@
+//! Finding entries by name anywhere below a folder.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+use crate::dir_loader::{BATCH_INTERVAL, BATCH_SIZE};
+use crate::error::FileNewerError;
+use crate::file_manager::{file_info_from, FileInfo};
+use crate::filter::NameMatcher;
+use crate::job::Job;
+use crate::walk::Walk;
+
+/// An entry found by a [`NameSearch`]
+#[derive(Debug)]
+pub struct SearchHit {
+    /// Where the entry is, relative to the folder the search started from
+    pub relative_path: PathBuf,
+    /// The entry itself
+    pub file: FileInfo,
+}
+
+/// What a [`NameSearch`] has to report since it was last polled
+#[derive(Debug)]
+pub enum SearchEvent {
+    /// More matches, in the order they were found, and folders that could not be read
+    Batch {
+        /// The matches
+        hits: Vec<SearchHit>,
+        /// Folders and entries that could not be read
+        errors: Vec<FileNewerError>,
+    },
+    /// The whole tree has been searched
+    Done,
+    /// The folder the search started from could not be opened
+    Failed(FileNewerError),
+}
+
+/// Walks a directory tree on a worker thread, streaming back every entry whose name matches.
+///
+/// Symlinks are reported but never followed, hidden entries are skipped, along with
+/// everything inside them, unless `inc_hidden` is set. Dropping the search cancels it.
+pub struct NameSearch {
+    root: PathBuf,
+    job: Job<SearchEvent>,
+}
+
+impl NameSearch {
+    /// Starts searching everything below `root` for entries `matcher` matches
+    pub fn spawn(root: PathBuf, matcher: NameMatcher, inc_hidden: bool) -> Self {
+        let worker_root = root.clone();
+        let job = Job::spawn(|event| !matches!(event, SearchEvent::Batch { .. }), move |sender, cancelled| {
+            let mut hits = Vec::new();
+            let mut errors = Vec::new();
+            let mut batch_started = Instant::now();
+            let walk = match Walk::new(&worker_root, inc_hidden) {
+                Ok(walk) => walk,
+                Err(e) => {
+                    let _ = sender.send(SearchEvent::Failed(e));
+                    return;
+                }
+            };
+            for entry in walk {
+                if cancelled.load(Ordering::Relaxed) { return; }
+                let found = entry.and_then(|entry| Ok((file_info_from(&entry.path, entry.meta)?, entry.relative_path)));
+                match found {
+                    Ok((file, relative_path)) => {
+                        if matcher.matches(&file) { hits.push(SearchHit { relative_path, file }); }
+                    }
+                    Err(e) => { errors.push(e) }
+                }
+                let pending = hits.len() + errors.len();
+                if pending >= BATCH_SIZE || (pending > 0 && batch_started.elapsed() >= BATCH_INTERVAL) {
+                    let batch = SearchEvent::Batch {
+                        hits: std::mem::take(&mut hits), errors: std::mem::take(&mut errors),
+                    };
+                    // the receiver only goes away when the search is dropped
+                    if sender.send(batch).is_err() { return; }
+                    batch_started = Instant::now();
+                }
+            }
+            if !hits.is_empty() || !errors.is_empty() {
+                let _ = sender.send(SearchEvent::Batch { hits, errors });
+            }
+            let _ = sender.send(SearchEvent::Done);
+        });
+        Self { root, job }
+    }
+
+    /// The folder the search started from
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Everything the worker has sent since the last poll, never blocks
+    pub fn poll(&mut self) -> Vec<SearchEvent> {
+        self.job.poll()
+    }
+
+    /// `true` once `Done` or `Failed` has been polled, or the search was cancelled
+    pub fn is_finished(&self) -> bool {
+        self.job.is_finished()
+    }
+
+    /// Stops the worker at the next entry, hits already polled are kept by the caller
+    pub fn cancel(&mut self) {
+        self.job.cancel();
+    }
+}
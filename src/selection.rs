@@ -0,0 +1,107 @@
+//! Which entries of a listing are selected.
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+
+/// The selected entries of a listing.
+///
+/// Entries are kept by name rather than position, so the selection survives the listing being
+/// sorted, filtered or having entries come and go.
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Default)]
+#[derive(Debug)]
+pub struct Selection {
+    names: HashSet<OsString>,
+    // the entry last clicked, Shift ranges start from it
+    anchor: Option<OsString>,
+}
+
+impl Selection {
+    /// `true` when `name` is selected
+    pub fn contains(&self, name: &OsStr) -> bool {
+        self.names.contains(name)
+    }
+
+    /// How many entries are selected
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// `true` when nothing is selected
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The entry a range selection starts from, the one last clicked
+    pub fn anchor(&self) -> Option<&OsStr> {
+        self.anchor.as_deref()
+    }
+
+    /// The selected names in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &OsStr> {
+        self.names.iter().map(OsString::as_os_str)
+    }
+
+    /// Selects nothing, forgetting the anchor too
+    pub fn clear(&mut self) {
+        self.names.clear();
+        self.anchor = None;
+    }
+
+    /// Selects `name` and nothing else, like a plain click
+    pub fn select_only(&mut self, name: &OsStr) {
+        self.names.clear();
+        self.names.insert(name.to_os_string());
+        self.anchor = Some(name.to_os_string());
+    }
+
+    /// Adds or removes `name` leaving the rest as it was, like a Ctrl click
+    pub fn toggle(&mut self, name: &OsStr) {
+        if !self.names.remove(name) { self.names.insert(name.to_os_string()); }
+        self.anchor = Some(name.to_os_string());
+    }
+
+    /// Selects every entry from the anchor to `name` in the order they are shown, like a Shift click.
+    ///
+    /// Without an anchor in `shown` only `name` is selected.
+    pub fn select_range<'a>(&mut self, shown: impl IntoIterator<Item = &'a OsStr>, name: &OsStr) {
+        let Some(anchor) = self.anchor.clone() else { return self.select_only(name) };
+        let mut range = Vec::new();
+        let mut inside = false;
+        let mut found_anchor = false;
+        for shown_name in shown {
+            let is_end = shown_name == anchor || shown_name == name;
+            found_anchor |= shown_name == anchor;
+            if is_end && !inside {
+                inside = true;
+                range.push(shown_name);
+                // a range of one when the anchor is what was clicked
+                if anchor == name { break; }
+            } else if inside {
+                range.push(shown_name);
+                if is_end { break; }
+            }
+        }
+        if !found_anchor { return self.select_only(name); }
+        self.names = range.into_iter().map(OsStr::to_os_string).collect();
+    }
+
+    /// Selects all of `shown`
+    pub fn select_all<'a>(&mut self, shown: impl IntoIterator<Item = &'a OsStr>) {
+        self.names.extend(shown.into_iter().map(OsStr::to_os_string));
+    }
+
+    /// Selects those of `shown` that are not selected and unselects the rest
+    pub fn invert<'a>(&mut self, shown: impl IntoIterator<Item = &'a OsStr>) {
+        let old = std::mem::take(&mut self.names);
+        self.names = shown.into_iter().filter(|name| !old.contains(*name)).map(OsStr::to_os_string).collect();
+        if self.anchor.as_ref().is_some_and(|anchor| !self.names.contains(anchor)) { self.anchor = None; }
+    }
+
+    /// Keeps only the names `keep` returns `true` for, used to drop entries that have gone
+    pub fn retain(&mut self, mut keep: impl FnMut(&OsStr) -> bool) {
+        self.names.retain(|name| keep(name));
+        if self.anchor.as_deref().is_some_and(|anchor| !keep(anchor)) { self.anchor = None; }
+    }
+}
@@ -1,12 +1,47 @@
 #[cfg(test)]
 mod testing_file_manager{
+    use std::path::{Path, PathBuf};
+
+    // a fresh folder in the system temp dir, removed with everything in it when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let dir = std::env::temp_dir().join(format!("filenewer_{}_{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl std::ops::Deref for TempDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TempDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
     #[test]
-    fn evaluate_path_vars() {
+    #[cfg(windows)]
+    fn evaluate_path_vars_windows() {
         let epv =|vr:&str| { // epv - evaluate path value
             crate::file_manager::evaluate_path_vars(vr).unwrap()
         };
         let test_eq = |in_str:&str, out_str:String | {
-                assert_eq!(epv(in_str), out_str.to_owned());
+                assert_eq!(epv(in_str), PathBuf::from(out_str));
             };
 
         let user_pth = |ext: &str| -> String {
@@ -23,4 +58,801 @@ mod testing_file_manager{
         test_eq("/users", "C:\\users\\".to_string());
         test_eq("/users\\public", "C:\\users\\public\\".to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(unix)]
+    fn evaluate_path_vars_unix() {
+        // FILENEWER_TEST_DIR is only seen by this lookup, the environment itself is left alone
+        let var = |name: &str| match name {
+            "FILENEWER_TEST_DIR" => Ok("local".to_string()),
+            _ => std::env::var(name),
+        };
+        let epv =|vr:&str| { // epv - evaluate path value
+            crate::file_manager::evaluate_path_vars_with(vr, &var)
+        };
+        let test_eq = |in_str:&str, out_str:String | {
+                assert_eq!(epv(in_str).unwrap(), PathBuf::from(out_str));
+            };
+
+        let home_pth = |ext: &str| -> String {
+            let mut home = std::env::var("HOME").unwrap();
+            home.push_str(ext);
+            home};
+
+        test_eq("~", home_pth(""));
+        test_eq("~/", home_pth(""));
+        test_eq("~/Some/Path", home_pth("/Some/Path"));
+        test_eq("~//Some/./Path/", home_pth("/Some/Path"));
+        test_eq("$HOME/Some", home_pth("/Some"));
+        test_eq("${HOME}/Some", home_pth("/Some"));
+        test_eq("/usr/$FILENEWER_TEST_DIR/bin", "/usr/local/bin".to_string());
+        test_eq("/usr/${FILENEWER_TEST_DIR}_x", "/usr/local_x".to_string());
+        test_eq("/usr/local/../bin", "/usr/bin".to_string());
+        test_eq("/../..", "/".to_string());
+        test_eq("/cost/$5", "/cost/$5".to_string());
+        test_eq("~no_user_by_this_name/x", "~no_user_by_this_name/x".to_string());
+        #[cfg(target_os = "linux")]
+        test_eq("~root/x", "/root/x".to_string());
+        assert!(epv("/$FILENEWER_UNSET_TEST_VAR").is_err());
+    }
+
+    #[test]
+    fn missing_dir_is_not_found() {
+        let pth = std::env::temp_dir().join("filenewer_no_such_dir");
+        match crate::file_manager::get_files_in_dir(&pth, &true) {
+            Err(crate::error::FileNewerError::NotFound(p)) => assert_eq!(p, pth),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinks_are_detected() {
+        use crate::file_manager::FileType;
+        let dir = TempDir::new("links");
+        std::fs::write(dir.join("target.txt"), "x").unwrap();
+        std::os::unix::fs::symlink("target.txt", dir.join("link")).unwrap();
+        std::os::unix::fs::symlink("missing.txt", dir.join("broken")).unwrap();
+        std::os::unix::fs::symlink("loop_b", dir.join("loop_a")).unwrap();
+        std::os::unix::fs::symlink("loop_a", dir.join("loop_b")).unwrap();
+
+        let listing = crate::file_manager::get_files_in_dir(&dir, &true).unwrap();
+        let find = |name: &str| listing.files.iter().find(|f| f.file_name == name).unwrap();
+        assert_eq!(find("link").file_type, FileType::Link);
+        assert_eq!(find("link").link_target, Some(PathBuf::from("target.txt")));
+        assert_eq!(find("link").resolve_link_target(&dir), Some(dir.join("target.txt")));
+        assert_eq!(find("broken").file_type, FileType::BrokenLink);
+        assert_eq!(find("loop_a").file_type, FileType::BrokenLink);
+        assert!(find("target.txt").link_target.is_none());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn special_files_have_their_own_type() {
+        use crate::file_manager::FileType;
+        let listing = crate::file_manager::get_files_in_dir(&"/dev", &true).unwrap();
+        let null = listing.files.iter().find(|f| f.file_name == "null").unwrap();
+        assert_eq!(null.file_type, FileType::CharDevice);
+        assert!(!null.has_size());
+
+        let dir = TempDir::new("fifo");
+        let fifo = std::ffi::CString::new(dir.join("pipe").into_os_string().into_encoded_bytes()).unwrap();
+        // SAFETY: fifo is a valid nul terminated path
+        assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+        let listing = crate::file_manager::get_files_in_dir(&dir, &true).unwrap();
+        assert_eq!(listing.files[0].file_type, FileType::Fifo);
+        assert_eq!(listing.files[0].single_char_desc(), "p");
+    }
+
+    #[test]
+    fn format_mode() {
+        use crate::file_manager::format_mode;
+        assert_eq!(format_mode(0o040755), "drwxr-xr-x");
+        assert_eq!(format_mode(0o100644), "-rw-r--r--");
+        assert_eq!(format_mode(0o104755), "-rwsr-xr-x");
+        assert_eq!(format_mode(0o102644), "-rw-r-Sr--");
+        assert_eq!(format_mode(0o041777), "drwxrwxrwt");
+        assert_eq!(format_mode(0o041776), "drwxrwxrwT");
+        assert_eq!(format_mode(0o120777), "lrwxrwxrwx");
+        assert_eq!(format_mode(0o020620), "crw--w----");
+    }
+
+    #[test]
+    fn dir_loader_streams_every_entry() {
+        use crate::dir_loader::{DirLoader, LoadEvent};
+        let dir = TempDir::new("loader");
+        for i in 0..1500 { std::fs::write(dir.join(format!("file{}", i)), "").unwrap(); }
+
+        let mut loader = DirLoader::spawn(dir.to_path_buf(), true);
+        let mut files = 0;
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !loader.is_finished() {
+            if std::time::Instant::now() > deadline { panic!("loading the folder did not finish in time"); }
+            for event in loader.poll() {
+                match event {
+                    LoadEvent::Batch(listing) => files += listing.files.len(),
+                    LoadEvent::Done => {}
+                    LoadEvent::Failed(e) => panic!("{}", e),
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(files, 1500);
+
+        let gone = dir.to_path_buf();
+        drop(dir);
+        let mut missing = DirLoader::spawn(gone, true);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !missing.is_finished() {
+            if std::time::Instant::now() > deadline { panic!("loading a missing folder did not finish in time"); }
+            if let Some(LoadEvent::Failed(_)) = missing.poll().pop() { return; }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        panic!("loading a missing folder should fail");
+    }
+
+    #[test]
+    fn watcher_changes_are_applied_by_name() {
+        use crate::dir_watcher::{refresh_entries, DirWatcher};
+        let dir = TempDir::new("watch");
+        std::fs::write(dir.join("keep"), "").unwrap();
+        std::fs::write(dir.join("grow"), "").unwrap();
+        std::fs::write(dir.join("remove"), "").unwrap();
+        let mut files = crate::file_manager::get_files_in_dir(&dir, &false).unwrap().files;
+
+        let watcher = DirWatcher::watch(&dir).unwrap();
+        std::fs::write(dir.join("grow"), "grown").unwrap();
+        std::fs::remove_file(dir.join("remove")).unwrap();
+        std::fs::write(dir.join("new"), "").unwrap();
+        std::fs::write(dir.join(".hidden"), "").unwrap();
+
+        let mut changed = std::collections::BTreeSet::new();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while changed.len() < 4 && std::time::Instant::now() < deadline {
+            changed.extend(watcher.poll().paths);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        assert!(refresh_entries(&mut files, &changed, false).is_empty());
+
+        let mut names: Vec<_> = files.iter().map(|f| f.file_name.to_str().unwrap()).collect();
+        names.sort();
+        assert_eq!(names, ["grow", "keep", "new"]);
+        assert_eq!(files.iter().find(|f| f.file_name == "grow").unwrap().file_size, 5);
+    }
+
+    #[test]
+    fn dir_size_is_recursive() {
+        use std::sync::atomic::AtomicBool;
+        let dir = TempDir::new("size");
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::write(dir.join("top"), "12345").unwrap();
+        std::fs::write(dir.join("a/mid"), "123").unwrap();
+        std::fs::write(dir.join("a/b/deep"), "1").unwrap();
+
+        let size = crate::dir_size::dir_size(&dir, &AtomicBool::new(false)).unwrap();
+        assert_eq!((size.bytes, size.files), (9, 3));
+        assert!(crate::dir_size::dir_size(&dir, &AtomicBool::new(true)).is_none());
+    }
+
+    #[test]
+    fn size_format() {
+        use crate::formatting::{SizeFormat, SizeUnits};
+        let fmt = |units, precision, bytes| SizeFormat { units, precision }.format(bytes);
+        assert_eq!(fmt(SizeUnits::Bytes, None, 1_234_567), "1234567");
+        assert_eq!(fmt(SizeUnits::Si, None, 999), "999 B");
+        assert_eq!(fmt(SizeUnits::Si, None, 1_234), "1.23 kB");
+        assert_eq!(fmt(SizeUnits::Si, None, 12_345_678), "12.3 MB");
+        assert_eq!(fmt(SizeUnits::Si, Some(0), 1_500), "2 kB");
+        assert_eq!(fmt(SizeUnits::Iec, None, 1_024), "1.00 KiB");
+        assert_eq!(fmt(SizeUnits::Iec, None, 123 * 1024 * 1024), "123 MiB");
+        assert_eq!(fmt(SizeUnits::Iec, None, 1024 * 1024 - 1), "1.00 MiB");
+        assert_eq!(fmt(SizeUnits::Iec, Some(1), 1536), "1.5 KiB");
+    }
+
+    #[test]
+    fn date_format() {
+        use std::time::{Duration, UNIX_EPOCH};
+        use crate::formatting::{DateFormat, DateStyle};
+        let fmt = |style, pattern: &str, time| DateFormat { style, custom_pattern: pattern.to_string(), utc: true }
+            .format_at(time, UNIX_EPOCH + Duration::from_secs(400 * 24 * 3600));
+        let ago = |secs: u64| Some(UNIX_EPOCH + Duration::from_secs(400 * 24 * 3600 - secs));
+
+        assert_eq!(fmt(DateStyle::Standard, "", Some(UNIX_EPOCH)), "1970-01-01 00:00:00");
+        assert_eq!(fmt(DateStyle::Iso8601, "", Some(UNIX_EPOCH)), "1970-01-01T00:00:00+00:00");
+        assert_eq!(fmt(DateStyle::Custom, "%d/%m/%Y", Some(UNIX_EPOCH)), "01/01/1970");
+        assert_eq!(fmt(DateStyle::Custom, "%Q", Some(UNIX_EPOCH)), "invalid format");
+        assert_eq!(fmt(DateStyle::Standard, "", None), "-");
+        assert_eq!(fmt(DateStyle::Relative, "", ago(10)), "just now");
+        assert_eq!(fmt(DateStyle::Relative, "", ago(60)), "1 minute ago");
+        assert_eq!(fmt(DateStyle::Relative, "", ago(3 * 3600 + 59)), "3 hours ago");
+        assert_eq!(fmt(DateStyle::Relative, "", ago(2 * 24 * 3600)), "2 days ago");
+        assert_eq!(fmt(DateStyle::Relative, "", ago(399 * 24 * 3600)), "1 year ago");
+        assert_eq!(fmt(DateStyle::Relative, "", Some(UNIX_EPOCH + Duration::from_secs(401 * 24 * 3600))),
+                   "in 1 day");
+    }
+
+    fn fixture(name: &str, file_type: crate::FileType, file_size: u64) -> crate::FileInfo {
+        crate::FileInfo {
+            file_type,
+            can_be_written: true,
+            file_name: name.into(),
+            file_ext: name.rsplit_once('.').map(|(_, ext)| ext.to_string()),
+            file_size,
+            last_access: None,
+            last_modification: None,
+            creation_time: None,
+            is_hidden: false,
+            link_target: None,
+            mode: None,
+            owner: None,
+            group: None,
+            dir_size: None,
+        }
+    }
+
+    #[test]
+    fn multi_key_sort() {
+        use crate::{DisplayOptions, FileType, SortBy, SortKey};
+        let names = |files: &[crate::FileInfo]| -> Vec<String> {
+            files.iter().map(|f| f.file_name.to_str().unwrap().to_string()).collect()
+        };
+        let mut files = vec![
+            fixture("b.txt", FileType::File, 10),
+            fixture("docs", FileType::Dir, 0),
+            fixture("a.rs", FileType::File, 10),
+            fixture("c", FileType::File, 5),
+            fixture("a.txt", FileType::File, 20),
+        ];
+        let mut options = DisplayOptions { sort_by: SortBy::Size, ..Default::default() };
+
+        // equal sizes keep the order they came in, the directory has no size so goes last
+        options.sort(&mut files);
+        assert_eq!(names(&files), ["c", "b.txt", "a.rs", "a.txt", "docs"]);
+        options.filter_dec = true;
+        options.sort(&mut files);
+        assert_eq!(names(&files), ["a.txt", "b.txt", "a.rs", "c", "docs"]);
+
+        options.dirs_first = true;
+        options.then_by = vec![SortKey { by: SortBy::Name, descending: false }];
+        options.sort(&mut files);
+        assert_eq!(names(&files), ["docs", "a.txt", "a.rs", "b.txt", "c"]);
+
+        options.sort_by = SortBy::Ext;
+        options.filter_dec = false;
+        options.dirs_first = false;
+        options.sort(&mut files);
+        assert_eq!(names(&files), ["a.rs", "a.txt", "b.txt", "c", "docs"]);
+    }
+
+    #[test]
+    fn name_order() {
+        use std::cmp::Ordering;
+        use std::ffi::OsStr;
+        use crate::{CaseOrder, NameOrder};
+        let order = |natural, case, ignore_accents| NameOrder { natural, case, ignore_accents };
+        let cmp = |order: NameOrder, a: &str, b: &str| order.compare(OsStr::new(a), OsStr::new(b));
+
+        let bytes = order(false, CaseOrder::Sensitive, false);
+        assert_eq!(cmp(bytes, "file10", "file2"), Ordering::Less);
+        assert_eq!(cmp(bytes, "Zeta", "alpha"), Ordering::Less);
+
+        let natural = order(true, CaseOrder::Sensitive, false);
+        assert_eq!(cmp(natural, "file10", "file2"), Ordering::Greater);
+        assert_eq!(cmp(natural, "file2b", "file02a"), Ordering::Greater);
+        assert_eq!(cmp(natural, "v99999999999999999999999", "v100000000000000000000000"), Ordering::Less);
+
+        assert_eq!(cmp(order(false, CaseOrder::AsciiInsensitive, false), "Zeta", "alpha"), Ordering::Greater);
+        assert_eq!(cmp(order(false, CaseOrder::AsciiInsensitive, false), "Ärger", "ärger"), Ordering::Less);
+        assert_eq!(cmp(order(false, CaseOrder::Folded, false), "Ärger", "ärgern"), Ordering::Less);
+        assert_eq!(cmp(order(false, CaseOrder::Folded, false), "éclair", "fig"), Ordering::Greater);
+        assert_eq!(cmp(order(false, CaseOrder::Folded, true), "éclair", "fig"), Ordering::Less);
+        // names equal apart from case still get a fixed order
+        assert_eq!(cmp(NameOrder::default(), "README", "readme"), Ordering::Less);
+    }
+
+    #[test]
+    fn header_clicks_toggle_sort() {
+        use crate::{DisplayOptions, SortBy};
+        let mut options = DisplayOptions::default();
+        options.toggle_sort(SortBy::Size, true);
+        assert_eq!(options.sort_position(&SortBy::Size), Some((0, false)));
+        options.toggle_sort(SortBy::Size, false);
+        assert_eq!(options.sort_position(&SortBy::Size), Some((0, true)));
+
+        options.toggle_sort(SortBy::Name, true);
+        options.toggle_sort(SortBy::ModDate, true);
+        options.toggle_sort(SortBy::Name, true);
+        assert_eq!(options.sort_position(&SortBy::Name), Some((1, true)));
+        assert_eq!(options.sort_position(&SortBy::ModDate), Some((2, false)));
+
+        options.toggle_sort(SortBy::Ext, false);
+        assert_eq!(options.sort_position(&SortBy::Ext), Some((0, false)));
+        assert_eq!(options.sort_position(&SortBy::Size), None);
+        assert!(options.then_by.is_empty());
+    }
+
+    #[test]
+    fn columns_follow_toggles_and_order() {
+        use crate::{Column, DisplayOptions};
+        let mut options = DisplayOptions {
+            show_file_type: false, show_creation: false, show_last_acc: true,
+            show_permissions: false, show_owner: false, show_group: false, ..Default::default()
+        };
+        assert_eq!(options.columns(), [Column::Name, Column::Ext, Column::Modified, Column::Accessed, Column::Size]);
+
+        options.move_column(Column::Size, Column::Name);
+        options.move_column(Column::Ext, Column::Accessed);
+        assert_eq!(options.columns(), [Column::Size, Column::Name, Column::Modified, Column::Accessed, Column::Ext]);
+        options.show_file_ext = false;
+        assert_eq!(options.columns(), [Column::Size, Column::Name, Column::Modified, Column::Accessed]);
+    }
+
+    #[test]
+    fn display_settings_are_kept() {
+        use std::collections::HashMap;
+        use crate::{format_widths, parse_widths, Column, DisplayOptions};
+        let mut options = DisplayOptions { show_hidden: true, show_file_ext: false, calc_dir_sizes: true, ..Default::default() };
+        options.move_column(Column::Size, Column::Type);
+        let mut kept = DisplayOptions::default();
+        kept.apply_settings(&options.settings());
+        assert!(kept.show_hidden && !kept.show_file_ext && kept.calc_dir_sizes);
+        assert_eq!(kept.column_order, options.column_order);
+
+        // unknown keys and columns are skipped, columns left out are put back at the end
+        kept.apply_settings("show_hidden=maybe\nnew_option=true\ncolumns=name,bogus,type");
+        assert!(kept.show_hidden);
+        assert_eq!(kept.column_order[..3], [Column::Name, Column::Type, Column::Ext]);
+        assert_eq!(kept.column_order.len(), Column::ALL.len());
+
+        let widths = HashMap::from([(Column::Name, 212.5), (Column::Size, 60.0)]);
+        assert_eq!(parse_widths(&format_widths(&widths)), widths);
+        assert_eq!(parse_widths("name=80,bogus=5,size=wide"), HashMap::from([(Column::Name, 80.0)]));
+    }
+
+    #[test]
+    fn ls_arguments() {
+        use std::ffi::OsString;
+        use crate::{parse_ls_args, Column, DateStyle, DisplayOptions, OutputFormat, SizeUnits, SortBy, SortKey};
+        let parse = |args: &[&str]| parse_ls_args(&args.iter().map(OsString::from).collect::<Vec<_>>());
+
+        let opts = parse(&[]).unwrap().unwrap();
+        assert_eq!(opts.path, ".");
+        // the explorer and ls start out showing the same columns
+        assert_eq!(opts.columns, DisplayOptions::default().columns());
+        assert_eq!(opts.format, OutputFormat::Plain);
+
+        let opts = parse(&["~/docs", "--sort", "size,name", "--desc", "--hidden", "--dirs-first", "--columns", "name,size",
+                           "--format", "csv", "--size", "bytes", "--date", "%Y", "--utc"]).unwrap().unwrap();
+        assert_eq!(opts.path, "~/docs");
+        let options = &opts.display_options;
+        assert_eq!(options.sort_by, SortBy::Size);
+        assert_eq!(options.then_by, [SortKey { by: SortBy::Name, descending: false }]);
+        assert!(options.filter_dec && options.show_hidden && options.dirs_first && options.date_format.utc);
+        assert_eq!(opts.columns, [Column::Name, Column::Size]);
+        assert_eq!(opts.format, OutputFormat::Csv);
+        assert_eq!(options.size_format.units, SizeUnits::Bytes);
+        assert_eq!((options.date_format.style, options.date_format.custom_pattern.as_str()), (DateStyle::Custom, "%Y"));
+
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert_eq!(parse(&["--bogus"]).err().unwrap(), "unknown option `--bogus`");
+        assert_eq!(parse(&["--sort"]).err().unwrap(), "--sort needs a value");
+        assert_eq!(parse(&["--sort", "colour"]).err().unwrap(), "unknown sort key `colour`");
+        assert_eq!(parse(&["--columns", "name,colour"]).err().unwrap(), "unknown column `colour`");
+        assert_eq!(parse(&["--format", "xml"]).err().unwrap(), "unknown format `xml`");
+        assert_eq!(parse(&["a", "b"]).err().unwrap(), "unexpected argument `b`");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStringExt;
+            // a path that is not UTF-8 is taken as it is
+            let path = OsString::from_vec(b"/tmp/\xff".to_vec());
+            assert_eq!(parse_ls_args(std::slice::from_ref(&path)).unwrap().unwrap().path, path);
+            let bad_value = [OsString::from("--format"), path];
+            assert_eq!(parse_ls_args(&bad_value).err().unwrap(), "--format needs a value that is valid UTF-8");
+        }
+    }
+
+    #[test]
+    fn ls_output_formats() {
+        use std::ffi::OsString;
+        use crate::{parse_ls_args, FileType};
+        let files = [fixture("notes.txt", FileType::File, 1500), fixture("a, \"b\"", FileType::File, 2)];
+        let render = |format: &str| {
+            let args = ["--columns", "name,size", "--size", "bytes", "--format", format].map(OsString::from);
+            parse_ls_args(&args).unwrap().unwrap().render(&files)
+        };
+        assert_eq!(render("plain"), "File Name  File Size\nnotes.txt  1500\na, \"b\"     2\n");
+        assert_eq!(render("json"),
+                   "[\n  {\"name\": \"notes.txt\", \"size\": \"1500\"},\n  {\"name\": \"a, \\\"b\\\"\", \"size\": \"2\"}\n]\n");
+        assert_eq!(render("csv"), "name,size\nnotes.txt,1500\n\"a, \"\"b\"\"\",2\n");
+        let empty = parse_ls_args(&[OsString::from("--format"), OsString::from("json")]).unwrap().unwrap();
+        assert_eq!(empty.render(&[]), "[]\n");
+    }
+
+    #[test]
+    fn name_filter() {
+        use crate::{FileType, MatchMode, NameFilter, TypeFilter};
+        let filter = |pattern: &str, mode, case_sensitive| NameFilter {
+            pattern: pattern.to_string(), mode, case_sensitive, types: TypeFilter::All,
+        }.compile().unwrap();
+
+        assert!(filter("MAIN", MatchMode::Substring, false).matches_name("src_main.rs"));
+        assert!(!filter("MAIN", MatchMode::Substring, true).matches_name("src_main.rs"));
+        assert!(filter("a.b", MatchMode::Substring, false).matches_name("a.b"));
+        assert!(!filter("a.b", MatchMode::Substring, false).matches_name("axb"));
+
+        assert!(filter("*.rs", MatchMode::Glob, false).matches_name("lib.rs"));
+        assert!(!filter("*.rs", MatchMode::Glob, false).matches_name("lib.rs.bak"));
+        assert!(filter("file?.[ch]", MatchMode::Glob, false).matches_name("file1.c"));
+        assert!(!filter("file?.[!ch]", MatchMode::Glob, false).matches_name("file1.c"));
+        assert!(filter("[]a]", MatchMode::Glob, false).matches_name("]"));
+        assert!(filter("a[b", MatchMode::Glob, false).matches_name("a[b"));
+
+        assert!(filter("^ma.n\\.rs$", MatchMode::Regex, false).matches_name("Main.rs"));
+        assert!(NameFilter { pattern: "(".to_string(), mode: MatchMode::Regex, ..Default::default() }
+            .compile().is_err());
+
+        assert!(filter("flmg", MatchMode::Fuzzy, false).matches_name("File_Manager.rs"));
+        assert!(!filter("gmlf", MatchMode::Fuzzy, false).matches_name("file_manager.rs"));
+
+        let dirs = NameFilter { types: TypeFilter::DirsOnly, ..Default::default() }.compile().unwrap();
+        assert!(dirs.matches(&fixture("docs", FileType::Dir, 0)));
+        assert!(!dirs.matches(&fixture("docs.txt", FileType::File, 0)));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn name_search_walks_the_tree() {
+        use std::path::PathBuf;
+        use crate::{MatchMode, NameFilter, NameSearch, SearchEvent};
+        let dir = TempDir::new("search");
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::create_dir_all(dir.join(".hidden")).unwrap();
+        for file in ["top.txt", "a/mid.txt", "a/b/deep.txt", "a/b/deep.rs", ".hidden/secret.txt"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let search = |inc_hidden| {
+            let filter = NameFilter { pattern: "*.txt".to_string(), mode: MatchMode::Glob, ..Default::default() };
+            let mut search = NameSearch::spawn(dir.to_path_buf(), filter.compile().unwrap(), inc_hidden);
+            let mut found = Vec::new();
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            while !search.is_finished() {
+                if std::time::Instant::now() > deadline { panic!("the name search did not finish in time"); }
+                for event in search.poll() {
+                    match event {
+                        SearchEvent::Batch { hits, .. } => found.extend(hits.into_iter().map(|hit| hit.relative_path)),
+                        SearchEvent::Done => {}
+                        SearchEvent::Failed(e) => panic!("{}", e),
+                    }
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            found.sort();
+            found
+        };
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(search(false), paths(&["a/b/deep.txt", "a/mid.txt", "top.txt"]));
+        assert_eq!(search(true), paths(&[".hidden/secret.txt", "a/b/deep.txt", "a/mid.txt", "top.txt"]));
+    }
+
+    #[test]
+    fn content_search_finds_lines() {
+        use crate::{search_file, ContentEvent, ContentQuery, ContentSearch};
+        let dir = TempDir::new("grep");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("notes.txt"), "one\ntwo\nNeedle three\nfour\nfive\nneedle six\n").unwrap();
+        std::fs::write(dir.join("sub/more.txt"), "a needle\r\n").unwrap();
+        std::fs::write(dir.join("blob.bin"), b"needle\0needle").unwrap();
+
+        let query = ContentQuery { pattern: "needle".to_string(), context: 1, ..Default::default() };
+        let matches = search_file(&dir.join("notes.txt"), &query.compile().unwrap(), 1).unwrap();
+        assert_eq!(matches.iter().map(|m| m.line_number).collect::<Vec<_>>(), [3, 6]);
+        assert_eq!(matches[0].before, ["two"]);
+        assert_eq!(matches[0].after, ["four"]);
+        assert!(matches[1].after.is_empty());
+        assert!(search_file(&dir.join("blob.bin"), &query.compile().unwrap(), 0).unwrap().is_empty());
+
+        let case_sensitive = ContentQuery { case_sensitive: true, ..query.clone() }.compile().unwrap();
+        assert_eq!(search_file(&dir.join("notes.txt"), &case_sensitive, 0).unwrap().len(), 1);
+        let regex = ContentQuery { pattern: "^a n.+e$".to_string(), regex: true, ..query.clone() };
+        assert_eq!(search_file(&dir.join("sub/more.txt"), &regex.compile().unwrap(), 0).unwrap().len(), 1);
+
+        let mut search = ContentSearch::spawn(dir.to_path_buf(), query.compile().unwrap(), 0, false);
+        let mut found = Vec::new();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while !search.is_finished() {
+            if std::time::Instant::now() > deadline { panic!("the content search did not finish in time"); }
+            for event in search.poll() {
+                match event {
+                    ContentEvent::Hit(hit) => found.push(hit.relative_path),
+                    ContentEvent::Unreadable(e) | ContentEvent::Failed(e) => panic!("{}", e),
+                    ContentEvent::Done => {}
+                }
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        found.sort();
+        assert_eq!(found, [PathBuf::from("notes.txt"), PathBuf::from("sub/more.txt")]);
+    }
+
+    #[test]
+    fn walk_visits_every_entry_once() {
+        use crate::walk::Walk;
+        let dir = TempDir::new("walk");
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::create_dir_all(dir.join(".hidden")).unwrap();
+        std::fs::write(dir.join("a/b/deep"), "").unwrap();
+        std::fs::write(dir.join(".hidden/inside"), "").unwrap();
+        std::fs::write(dir.join("top"), "").unwrap();
+
+        let walk = |inc_hidden| {
+            let mut paths: Vec<_> = Walk::new(&dir, inc_hidden).unwrap()
+                .map(|entry| entry.unwrap().relative_path).collect();
+            paths.sort();
+            paths
+        };
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        assert_eq!(walk(false), paths(&["a", "a/b", "a/b/deep", "top"]));
+        assert_eq!(walk(true), paths(&[".hidden", ".hidden/inside", "a", "a/b", "a/b/deep", "top"]));
+        assert!(Walk::new(&dir.join("top"), true).is_err());
+    }
+
+    #[test]
+    fn file_operations() {
+        use std::fs;
+        use crate::{conflicts, copy_entry, delete_entry, move_entry, rename_entry, ConflictPolicy, FileNewerError};
+        let dir = TempDir::new("ops");
+        fs::create_dir_all(dir.join("src/inner")).unwrap();
+        fs::create_dir_all(dir.join("dest")).unwrap();
+        fs::write(dir.join("src/inner/a.txt"), "a").unwrap();
+        fs::write(dir.join("notes.txt"), "new").unwrap();
+        fs::write(dir.join("dest/notes.txt"), "old").unwrap();
+
+        let copied = copy_entry(&dir.join("src"), &dir.join("dest"), ConflictPolicy::Skip).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(copied.join("inner/a.txt")).unwrap(), "a");
+        assert!(matches!(copy_entry(&dir.join("src"), &dir.join("src/inner"), ConflictPolicy::Skip),
+                         Err(FileNewerError::IntoItself(_))));
+
+        let notes = [dir.join("notes.txt")];
+        assert_eq!(conflicts(&notes, &dir.join("dest")), notes);
+        assert_eq!(copy_entry(&notes[0], &dir.join("dest"), ConflictPolicy::Skip).unwrap(), None);
+        assert_eq!(copy_entry(&notes[0], &dir.join("dest"), ConflictPolicy::KeepBoth).unwrap(),
+                   Some(dir.join("dest/notes (2).txt")));
+        // copying into the folder it is already in makes a second copy rather than overwriting itself
+        assert_eq!(copy_entry(&notes[0], &dir, ConflictPolicy::Overwrite).unwrap(), Some(dir.join("notes (2).txt")));
+        move_entry(&notes[0], &dir.join("dest"), ConflictPolicy::Overwrite).unwrap();
+        assert!(!notes[0].exists());
+        assert_eq!(fs::read_to_string(dir.join("dest/notes.txt")).unwrap(), "new");
+
+        assert!(matches!(rename_entry(&dir.join("dest/notes.txt"), "notes (2).txt"),
+                         Err(FileNewerError::AlreadyExists(_))));
+        assert!(matches!(rename_entry(&dir.join("dest/notes.txt"), "../escape"), Err(FileNewerError::InvalidName(_))));
+        assert_eq!(rename_entry(&dir.join("dest/notes.txt"), "renamed.txt").unwrap(), dir.join("dest/renamed.txt"));
+
+        delete_entry(&dir.join("dest/src")).unwrap();
+        assert!(!dir.join("dest/src").exists());
+        assert!(dir.join("src/inner/a.txt").exists());
+    }
+
+    #[test]
+    fn file_jobs_report_progress_and_what_was_done() {
+        use std::fs;
+        use crate::{ConflictPolicy, FileJob, FileJobEvent, FileOp, FileTask};
+        let dir = TempDir::new("file_job");
+        fs::create_dir_all(dir.join("src/inner")).unwrap();
+        fs::create_dir_all(dir.join("dest")).unwrap();
+        fs::write(dir.join("src/inner/a.txt"), "a").unwrap();
+        fs::write(dir.join("src/b.txt"), "b").unwrap();
+        fs::write(dir.join("dest/b.txt"), "mine").unwrap();
+
+        let run = |task| {
+            let mut job = FileJob::spawn(task);
+            let mut progress = Vec::new();
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            loop {
+                if std::time::Instant::now() > deadline { panic!("the file job did not finish in time"); }
+                for event in job.poll() {
+                    match event {
+                        FileJobEvent::Progress { done, total, .. } => progress.push((done, total)),
+                        FileJobEvent::Finished { ops, errors } => return (progress, ops, errors),
+                    }
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        };
+        let sources = vec![dir.join("src/inner"), dir.join("src/b.txt")];
+        let (progress, ops, errors) = run(FileTask::Paste {
+            sources, dest_dir: dir.join("dest"), cut: false, policy: ConflictPolicy::Skip,
+        });
+        assert_eq!(progress, [(0, 2), (1, 2)]);
+        assert!(errors.is_empty());
+        // the clash is skipped, so only the folder is recorded
+        assert!(matches!(ops.as_slice(), [FileOp::Copy { to, .. }] if *to == dir.join("dest/inner")));
+        assert_eq!(fs::read_to_string(dir.join("dest/inner/a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dir.join("dest/b.txt")).unwrap(), "mine");
+
+        let (_, ops, errors) = run(FileTask::Delete(vec![dir.join("dest/inner"), dir.join("dest/missing")]));
+        assert!(ops.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(!dir.join("dest/inner").exists());
+
+        // a stopped job still reports what it got done
+        let mut job = FileJob::spawn(FileTask::Delete((0..100).map(|i| dir.join(format!("none{}", i))).collect()));
+        job.stop();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let mut reported = false;
+        while !job.is_finished() {
+            if std::time::Instant::now() > deadline { panic!("the stopped file job did not finish in time"); }
+            reported |= job.poll().iter().any(|event| matches!(event, FileJobEvent::Finished { .. }));
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(reported);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn trash_and_restore() {
+        use std::fs;
+        use crate::{restore, FileNewerError};
+        use crate::trash::{list_trash_in, trash_entry_in};
+        let dir = TempDir::new("trash");
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        let trash_dir = dir.join("data/Trash");
+        let trash_entry = |path: &Path| trash_entry_in(&trash_dir, path);
+        let list_trash = || list_trash_in(Some(&trash_dir));
+        let original = dir.join("docs/a b%.txt");
+        fs::write(&original, "first").unwrap();
+
+        let first = trash_entry(&original).unwrap();
+        assert!(!original.exists());
+        assert_eq!(first.trash_dir, trash_dir);
+        let info = fs::read_to_string(trash_dir.join("info/a b%.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\n"));
+        assert!(info.contains("a%20b%25.txt\n"));
+        // a second entry of the same name gets its own place in the trash
+        fs::write(&original, "second").unwrap();
+        let second = trash_entry(&original).unwrap();
+        assert_ne!(second.name, first.name);
+
+        let (items, _) = list_trash();
+        let mut names: Vec<_> = items.iter()
+            .filter(|item| item.trash_dir == trash_dir)
+            .inspect(|item| assert!(item.original_path == original && item.deleted.is_some()))
+            .map(|item| item.name.clone())
+            .collect();
+        names.sort();
+        assert_eq!(names, [first.name.clone(), second.name.clone()]);
+
+        assert_eq!(restore(&second).unwrap(), original);
+        assert_eq!(fs::read_to_string(&original).unwrap(), "second");
+        assert!(matches!(restore(&first), Err(FileNewerError::AlreadyExists(_))));
+        fs::remove_file(&original).unwrap();
+        fs::remove_dir(dir.join("docs")).unwrap();
+        // the folder it came from is made again if it has gone
+        restore(&first).unwrap();
+        assert_eq!(fs::read_to_string(&original).unwrap(), "first");
+        assert!(list_trash().0.iter().all(|item| item.trash_dir != trash_dir));
+    }
+
+    #[test]
+    fn journal_undo_redo() {
+        use std::fs;
+        use crate::{FileNewerError, FileOp, Journal};
+        let dir = TempDir::new("journal");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        let mut journal = Journal::with_home_trash(dir.join("trash"));
+
+        fs::rename(dir.join("a.txt"), dir.join("b.txt")).unwrap();
+        journal.record("Rename a.txt".to_string(), vec![FileOp::Move { from: dir.join("a.txt"), to: dir.join("b.txt") }]);
+        fs::copy(dir.join("b.txt"), dir.join("c.txt")).unwrap();
+        fs::create_dir(dir.join("new")).unwrap();
+        journal.record("Copy and make".to_string(), vec![
+            FileOp::copied(dir.join("b.txt"), dir.join("c.txt")).unwrap(), FileOp::CreateDir(dir.join("new")),
+        ]);
+
+        assert!(journal.undo().errors.is_empty());
+        assert!(!dir.join("c.txt").exists() && !dir.join("new").exists());
+        assert_eq!(dir.join("trash/files/c.txt").exists(), crate::trash::SUPPORTED);
+        assert_eq!(journal.next_redo().unwrap().label, "Copy and make");
+        assert!(journal.undo().errors.is_empty());
+        assert!(dir.join("a.txt").exists() && !dir.join("b.txt").exists());
+        assert!(journal.next_undo().is_none());
+
+        assert!(journal.redo().errors.is_empty());
+        assert!(dir.join("b.txt").exists());
+        // something now in the way of the copy, so only the folder comes back
+        fs::write(dir.join("c.txt"), "mine").unwrap();
+        let replay = journal.redo();
+        assert!(matches!(replay.errors.as_slice(), [FileNewerError::AlreadyExists(_)]));
+        assert!(replay.dropped);
+        assert_eq!(fs::read_to_string(dir.join("c.txt")).unwrap(), "mine");
+        assert!(dir.join("new").is_dir());
+        assert_eq!(journal.next_undo().unwrap().ops, [FileOp::CreateDir(dir.join("new"))]);
+
+        // recording something new forgets what could have been redone
+        assert!(journal.undo().errors.is_empty());
+        journal.record("Other".to_string(), vec![FileOp::CreateDir(dir.join("other"))]);
+        assert!(journal.next_redo().is_none());
+        assert_eq!(journal.done().count(), 2);
+
+        // a copy changed since it was made is kept rather than undone
+        fs::remove_file(dir.join("c.txt")).unwrap();
+        fs::copy(dir.join("b.txt"), dir.join("c.txt")).unwrap();
+        journal.record("Copy b.txt".to_string(), vec![FileOp::copied(dir.join("b.txt"), dir.join("c.txt")).unwrap()]);
+        fs::write(dir.join("c.txt"), "changed").unwrap();
+        let replay = journal.undo();
+        assert!(matches!(replay.errors.as_slice(), [FileNewerError::Io { .. }]));
+        assert_eq!(fs::read_to_string(dir.join("c.txt")).unwrap(), "changed");
+        // nothing could be undone, so it is still there to try again
+        assert!(!replay.dropped);
+        assert_eq!(journal.next_undo().unwrap().label, "Copy b.txt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn new_entries_and_templates() {
+        use std::fs;
+        use crate::{create_dir, create_file, list_templates, FileOp, FileNewerError};
+        let dir = TempDir::new("new");
+        fs::create_dir_all(dir.join("config")).unwrap();
+        fs::create_dir_all(dir.join("My Templates/Project")).unwrap();
+        fs::write(dir.join("My Templates/Letter.txt"), "Dear").unwrap();
+        fs::write(dir.join("My Templates/.hidden"), "").unwrap();
+        fs::write(dir.join("config/user-dirs.dirs"),
+                  format!("# written by xdg-user-dirs-update\nXDG_TEMPLATES_DIR=\"{}\"\n", dir.join("My Templates").display()))
+            .unwrap();
+        let templates = crate::platform::templates_dir_in(&dir.join("config"), &dir).unwrap();
+        assert_eq!(templates, dir.join("My Templates"));
+        assert_eq!(list_templates(&templates), [dir.join("My Templates/Letter.txt"), dir.join("My Templates/Project")]);
+
+        assert_eq!(create_dir(&dir, "New Folder").unwrap(), dir.join("New Folder"));
+        assert_eq!(create_dir(&dir, "New Folder").unwrap(), dir.join("New Folder (2)"));
+        assert!(matches!(create_file(&dir, "a/b"), Err(FileNewerError::InvalidName(_))));
+        let file = create_file(&dir, "New File").unwrap();
+        assert_eq!(fs::metadata(&file).unwrap().len(), 0);
+
+        // a new file is only taken away again while it is still empty
+        fs::write(&file, "kept").unwrap();
+        assert!(FileOp::CreateFile(file.clone()).undo().is_err());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "kept");
+        fs::write(&file, "").unwrap();
+        FileOp::CreateFile(file.clone()).undo().unwrap();
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn selection() {
+        use std::ffi::OsStr;
+        use crate::Selection;
+        let shown: Vec<&OsStr> = ["a", "b", "c", "d", "e"].into_iter().map(OsStr::new).collect();
+        let names = |selection: &Selection| {
+            let mut names: Vec<_> = selection.names().map(|name| name.to_string_lossy().into_owned()).collect();
+            names.sort();
+            names
+        };
+        let mut selection = Selection::default();
+
+        selection.select_only(OsStr::new("b"));
+        selection.toggle(OsStr::new("d"));
+        assert_eq!(names(&selection), ["b", "d"]);
+        // ranges run from the last entry clicked, either way round, and replace the rest
+        selection.select_range(shown.iter().copied(), OsStr::new("a"));
+        assert_eq!(names(&selection), ["a", "b", "c", "d"]);
+        selection.select_range(shown.iter().copied(), OsStr::new("e"));
+        assert_eq!(names(&selection), ["d", "e"]);
+        assert_eq!(selection.anchor(), Some(OsStr::new("d")));
+
+        selection.toggle(OsStr::new("d"));
+        assert_eq!(names(&selection), ["e"]);
+        selection.invert(shown.iter().copied());
+        assert_eq!(names(&selection), ["a", "b", "c", "d"]);
+        assert_eq!(selection.anchor(), Some(OsStr::new("d")));
+        selection.retain(|name| name != "d");
+        assert_eq!(selection.anchor(), None);
+        selection.select_all(shown.iter().copied());
+        assert_eq!(selection.len(), 5);
+        selection.clear();
+        assert!(selection.is_empty());
+    }
+}
@@ -0,0 +1,287 @@
+//! Moving entries to the freedesktop.org trash and putting them back.
+
+use std::ffi::OsString;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use crate::error::FileNewerError;
+use crate::file_ops::move_path;
+use crate::platform;
+
+// the layout the freedesktop.org trash spec puts every trash directory in
+const FILES_DIR: &str = "files";
+const INFO_DIR: &str = "info";
+const INFO_EXT: &str = ".trashinfo";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Whether this platform has a trash FileNewer can use, only the freedesktop.org one for now
+pub const SUPPORTED: bool = cfg!(unix);
+
+/// Something sitting in a trash directory that can be put back with [`restore`]
+#[derive(PartialEq)]
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct TrashItem {
+    /// The name it was given inside the trash, not always the one it had before
+    pub name: OsString,
+    /// Where it was before being trashed
+    pub original_path: PathBuf,
+    /// `None` when the trash info had no readable date
+    pub deleted: Option<SystemTime>,
+    /// The trash directory holding it, with its `files` and `info` folders
+    pub trash_dir: PathBuf,
+}
+
+impl TrashItem {
+    /// Where the entry itself is while in the trash
+    pub fn path(&self) -> PathBuf {
+        self.trash_dir.join(FILES_DIR).join(&self.name)
+    }
+
+    fn info_path(&self) -> PathBuf {
+        let mut name = self.name.clone();
+        name.push(INFO_EXT);
+        self.trash_dir.join(INFO_DIR).join(name)
+    }
+}
+
+/// Moves `path` to the trash, returning what is needed to restore it.
+///
+/// Entries on the same file system as the home trash go there, anything else goes to the trash
+/// at the top of its own mount, `.Trash/$uid` or `.Trash-$uid`. When neither can be used the
+/// entry is copied over to the home trash instead.
+pub fn trash_entry(path: &Path) -> Result<TrashItem, FileNewerError> {
+    let Some(home_trash) = home_trash() else { return Err(FileNewerError::NoTrash(path.to_path_buf())) };
+    trash_entry_in(&home_trash, path)
+}
+
+// trash_entry with the home trash given rather than found from the environment
+pub(crate) fn trash_entry_in(home_trash: &Path, path: &Path) -> Result<TrashItem, FileNewerError> {
+    let Some(uid) = platform::user_id() else { return Err(FileNewerError::NoTrash(path.to_path_buf())) };
+    // the parent is resolved so the path recorded is where the user would look for it again
+    let (Some(parent), Some(file_name)) = (path.parent(), path.file_name()) else {
+        return Err(FileNewerError::InvalidName(path.to_string_lossy().into_owned()));
+    };
+    let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+    let path = fs::canonicalize(parent).map_err(|e| FileNewerError::from_io(parent, e))?.join(file_name);
+    let meta = fs::symlink_metadata(&path).map_err(|e| FileNewerError::from_io(&path, e))?;
+    let device = platform::device_id(&meta);
+
+    if device.is_some() && device == existing_device(home_trash) {
+        return move_to_trash(&path, home_trash, &path);
+    }
+    if let Some(top) = top_dir(&path, device) {
+        if let Some(trash_dir) = top_dir_trash(&top, uid) {
+            let relative = path.strip_prefix(&top).unwrap_or(&path);
+            return move_to_trash(&path, &trash_dir, relative);
+        }
+    }
+    move_to_trash(&path, home_trash, &path)
+}
+
+/// Everything in the home trash and the trash of every mounted file system, with anything
+/// that could not be read
+pub fn list_trash() -> (Vec<TrashItem>, Vec<FileNewerError>) {
+    list_trash_in(home_trash().as_deref())
+}
+
+// list_trash with the home trash given, None when there is none
+pub(crate) fn list_trash_in(home_trash: Option<&Path>) -> (Vec<TrashItem>, Vec<FileNewerError>) {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    for (trash_dir, top) in trash_dirs(home_trash) {
+        let info_dir = trash_dir.join(INFO_DIR);
+        let entries = match fs::read_dir(&info_dir) {
+            Ok(entries) => entries,
+            Err(e) => { errors.push(FileNewerError::from_io(&info_dir, e)); continue; }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => { errors.push(FileNewerError::from_io(&info_dir, e)); continue; }
+            };
+            match read_info(&entry.path(), &trash_dir, top.as_deref()) {
+                Ok(Some(item)) => { items.push(item) }
+                Ok(None) => {}
+                Err(e) => { errors.push(e) }
+            }
+        }
+    }
+    (items, errors)
+}
+
+/// Puts `item` back where it was trashed from, recreating any folders that have gone since.
+///
+/// Never replaces anything that has taken its place in the meantime.
+pub fn restore(item: &TrashItem) -> Result<PathBuf, FileNewerError> {
+    let dest = &item.original_path;
+    if fs::symlink_metadata(dest).is_ok() { return Err(FileNewerError::AlreadyExists(dest.clone())); }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| FileNewerError::from_io(parent, e))?;
+    }
+    move_path(&item.path(), dest)?;
+    let info = item.info_path();
+    fs::remove_file(&info).map_err(|e| FileNewerError::from_io(&info, e))?;
+    Ok(dest.clone())
+}
+
+fn home_trash() -> Option<PathBuf> {
+    platform::data_home().map(|dir| dir.join("Trash"))
+}
+
+// the device of path, or of the closest folder above it that exists yet
+fn existing_device(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find_map(|dir| fs::metadata(dir).ok())
+        .and_then(|meta| platform::device_id(&meta))
+}
+
+// the highest folder above path still on the same file system, where its mount is
+fn top_dir(path: &Path, device: Option<u64>) -> Option<PathBuf> {
+    device?;
+    let mut top = path.parent()?;
+    while let Some(parent) = top.parent() {
+        if existing_device(parent) != device { break; }
+        top = parent;
+    }
+    Some(top.to_path_buf())
+}
+
+// `$top/.Trash/$uid` when the admin has set up a shared `.Trash`, otherwise `$top/.Trash-$uid`
+fn top_dir_trash(top: &Path, uid: u32) -> Option<PathBuf> {
+    let shared = top.join(".Trash");
+    // a symlink or non sticky folder could let other users meddle, so the spec says to skip it
+    let shared_ok = fs::symlink_metadata(&shared)
+        .is_ok_and(|meta| meta.is_dir() && platform::is_sticky(&meta));
+    if shared_ok {
+        let own = shared.join(uid.to_string());
+        if fs::create_dir_all(&own).is_ok() { return Some(own); }
+    }
+    let own = top.join(format!(".Trash-{}", uid));
+    let meta = fs::create_dir_all(&own).and_then(|_| fs::symlink_metadata(&own));
+    meta.is_ok_and(|meta| meta.is_dir()).then_some(own)
+}
+
+// every trash directory that exists, with the top of the mount its paths are relative to
+fn trash_dirs(home_trash: Option<&Path>) -> Vec<(PathBuf, Option<PathBuf>)> {
+    let mut dirs: Vec<(PathBuf, Option<PathBuf>)> = home_trash.into_iter()
+        .filter(|dir| dir.is_dir())
+        .map(|dir| (dir.to_path_buf(), None))
+        .collect();
+    let Some(uid) = platform::user_id() else { return dirs };
+    for top in platform::mount_points() {
+        for dir in [top.join(".Trash").join(uid.to_string()), top.join(format!(".Trash-{}", uid))] {
+            if dir.is_dir() && !dirs.iter().any(|(known, _)| *known == dir) {
+                dirs.push((dir, Some(top.clone())));
+            }
+        }
+    }
+    dirs
+}
+
+// claims a free name with the info file first so two trashings can not pick the same one
+fn move_to_trash(path: &Path, trash_dir: &Path, recorded_path: &Path) -> Result<TrashItem, FileNewerError> {
+    let files_dir = trash_dir.join(FILES_DIR);
+    let info_dir = trash_dir.join(INFO_DIR);
+    for dir in [&files_dir, &info_dir] {
+        fs::create_dir_all(dir).map_err(|e| FileNewerError::from_io(dir, e))?;
+    }
+    let file_name = path.file_name().unwrap_or_default();
+    let deleted = SystemTime::now();
+    let contents = format!("[Trash Info]\nPath={}\nDeletionDate={}\n",
+                           percent_encode(recorded_path), DateTime::<Local>::from(deleted).format(DATE_FORMAT));
+    for n in 1.. {
+        let mut name = file_name.to_os_string();
+        if n > 1 { name.push(format!(".{}", n)); }
+        let mut info_name = name.clone();
+        info_name.push(INFO_EXT);
+        let info_path = info_dir.join(info_name);
+        if fs::symlink_metadata(files_dir.join(&name)).is_ok() { continue; }
+        let mut info = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(info) => info,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(FileNewerError::from_io(&info_path, e)),
+        };
+        let item = TrashItem {
+            name, original_path: path.to_path_buf(), deleted: Some(deleted), trash_dir: trash_dir.to_path_buf(),
+        };
+        let moved = info.write_all(contents.as_bytes())
+            .map_err(|e| FileNewerError::from_io(&info_path, e))
+            .and_then(|_| move_path(path, &item.path()));
+        if let Err(e) = moved {
+            // an info file without its entry would show up in the trash as something to restore
+            let _ = fs::remove_file(&info_path);
+            return Err(e);
+        }
+        return Ok(item);
+    }
+    unreachable!("ran out of names in {}", trash_dir.display())
+}
+
+// None for info files whose entry has gone, the spec says to leave those be
+fn read_info(info_path: &Path, trash_dir: &Path, top: Option<&Path>) -> Result<Option<TrashItem>, FileNewerError> {
+    let Some(name) = info_path.file_name().and_then(|name| name.to_str()).and_then(|name| name.strip_suffix(INFO_EXT))
+        else { return Ok(None) };
+    let contents = fs::read_to_string(info_path).map_err(|e| FileNewerError::from_entry_io(info_path, e))?;
+    let mut original_path = None;
+    let mut deleted = None;
+    for line in contents.lines().skip_while(|line| line.trim() != "[Trash Info]").skip(1) {
+        if line.starts_with('[') { break; }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        match key.trim() {
+            "Path" => { original_path = Some(platform::path_from_bytes(percent_decode(value.trim()))) }
+            "DeletionDate" => { deleted = parse_date(value.trim()) }
+            _ => {}
+        }
+    }
+    let Some(original_path) = original_path else {
+        return Err(FileNewerError::Io {
+            path: info_path.to_path_buf(), source: io::Error::new(io::ErrorKind::InvalidData, "no Path in trash info"),
+        });
+    };
+    let item = TrashItem {
+        name: OsString::from(name),
+        // relative paths are relative to the top of the mount the trash is on
+        original_path: match top { Some(top) if original_path.is_relative() => top.join(original_path), _ => original_path },
+        deleted,
+        trash_dir: trash_dir.to_path_buf(),
+    };
+    Ok(fs::symlink_metadata(item.path()).is_ok().then_some(item))
+}
+
+// the date is in local time with no zone, as the spec asks
+fn parse_date(date: &str) -> Option<SystemTime> {
+    let naive = NaiveDateTime::parse_from_str(date, DATE_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).earliest().map(SystemTime::from)
+}
+
+// every byte but unreserved characters and `/` is escaped, as in a URL path
+fn percent_encode(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.as_os_str().as_encoded_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(byte) { encoded.push(*byte as char); }
+        else { encoded.push_str(&format!("%{:02X}", byte)); }
+    }
+    encoded
+}
+
+fn percent_decode(encoded: &str) -> Vec<u8> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => { decoded.push(byte); i += 3; }
+            None => { decoded.push(bytes[i]); i += 1; }
+        }
+    }
+    decoded
+}
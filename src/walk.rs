@@ -0,0 +1,71 @@
+//! Visiting every entry below a folder.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::error::FileNewerError;
+use crate::platform;
+
+/// An entry found by a [`Walk`]
+pub(crate) struct WalkEntry {
+    /// Where the entry is
+    pub(crate) path: PathBuf,
+    /// `path` relative to the folder the walk started from
+    pub(crate) relative_path: PathBuf,
+    /// Read without following links
+    pub(crate) meta: fs::Metadata,
+}
+
+/// Every entry below a folder, depth first, without following symlinks.
+///
+/// Folders are read as the walk reaches them, so callers can stop part way through a huge tree.
+/// Hidden entries, and everything inside them, are skipped unless `inc_hidden` is set.
+pub(crate) struct Walk {
+    root: PathBuf,
+    inc_hidden: bool,
+    // folders found but not yet read, relative to root, most recently found first
+    to_visit: Vec<PathBuf>,
+    // the folder being read, relative to root
+    current: Option<(PathBuf, fs::ReadDir)>,
+}
+
+impl Walk {
+    /// Starts walking below `root`, failing only if `root` itself can not be read
+    pub(crate) fn new(root: &Path, inc_hidden: bool) -> Result<Self, FileNewerError> {
+        let entries = fs::read_dir(root).map_err(|e| FileNewerError::from_io(root, e))?;
+        Ok(Self { root: root.to_path_buf(), inc_hidden, to_visit: Vec::new(), current: Some((PathBuf::new(), entries)) })
+    }
+}
+
+impl Iterator for Walk {
+    /// Folders below `root` that can not be read come back as errors and the walk carries on
+    type Item = Result<WalkEntry, FileNewerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((relative_dir, entries)) = &mut self.current else {
+                let relative_dir = self.to_visit.pop()?;
+                let dir = self.root.join(&relative_dir);
+                match fs::read_dir(&dir) {
+                    Ok(entries) => { self.current = Some((relative_dir, entries)) }
+                    Err(e) => { return Some(Err(FileNewerError::from_io(&dir, e))) }
+                }
+                continue;
+            };
+            let entry = match entries.next() {
+                Some(Ok(entry)) => { entry }
+                Some(Err(e)) => { return Some(Err(FileNewerError::from_io(&self.root.join(relative_dir), e))) }
+                None => { self.current = None; continue; }
+            };
+            let path = entry.path();
+            // metadata on a dir entry does not follow links
+            let meta = match entry.metadata() {
+                Ok(meta) => { meta }
+                Err(e) => { return Some(Err(FileNewerError::from_entry_io(&path, e))) }
+            };
+            if !self.inc_hidden && platform::is_hidden(&path, &meta) { continue; }
+            let relative_path = relative_dir.join(entry.file_name());
+            if meta.is_dir() { self.to_visit.push(relative_path.clone()); }
+            return Some(Ok(WalkEntry { path, relative_path, meta }));
+        }
+    }
+}